@@ -0,0 +1,162 @@
+//! Structures and enumerations related to the Xcursor theme used to draw the cursors.
+
+use crate::geometry_manager::CursorIcon;
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+/// Single frame of a cursor image.
+pub struct CursorFrame {
+    pub size: pal::Size2D<u32>,
+    pub hotspot: pal::Offset2D<i32>,
+    /// Time in milliseconds the frame is shown before the next one.
+    pub delay: u32,
+    pub data: Vec<u8>,
+}
+impl CursorFrame {
+    /// Create the [SurfaceSource][screen_task::SurfaceSource] used to draw the frame.
+    pub fn source(&self) -> screen_task::SurfaceSource {
+        let info = screen_task::HostAllocationInfo {
+            size: [self.size.width, self.size.height],
+            stride: self.size.width * 4,
+            format: wgpu_engine::TextureFormat::Rgba8UnormSrgb,
+        };
+        let data = self.data.clone();
+        screen_task::SurfaceSource::HostAllocation { info, data }
+    }
+}
+
+#[derive(Debug)]
+/// Xcursor theme, loading the cursor images on demand.
+pub struct CursorTheme {
+    theme: xcursor::CursorTheme,
+    size: u32,
+    icons: HashMap<CursorIcon, Vec<CursorFrame>>,
+}
+impl CursorTheme {
+    /// Theme used when neither the configuration nor `XCURSOR_THEME` set one.
+    pub const DEFAULT_THEME: &'static str = "default";
+    /// Size used when neither the configuration nor `XCURSOR_SIZE` set one.
+    pub const DEFAULT_SIZE: u32 = 24;
+
+    /// Load the theme `name`, using the images closest to `size`.
+    pub fn load(name: &str, size: u32) -> Self {
+        let theme = xcursor::CursorTheme::load(name);
+        let icons = HashMap::new();
+        Self { theme, size, icons }
+    }
+
+    /// Load the theme `name` with `size`, each falling back to the `XCURSOR_THEME` and `XCURSOR_SIZE`
+    /// variables read by the clients, then to the defaults.
+    pub fn resolve(name: Option<&str>, size: Option<u32>) -> Self {
+        let name = name
+            .map(String::from)
+            .or_else(|| std::env::var("XCURSOR_THEME").ok())
+            .unwrap_or(String::from(Self::DEFAULT_THEME));
+        let size = size
+            .or_else(|| {
+                std::env::var("XCURSOR_SIZE")
+                    .ok()
+                    .map(|size| size.parse().ok())
+                    .flatten()
+            })
+            .unwrap_or(Self::DEFAULT_SIZE);
+        Self::load(&name, size)
+    }
+
+    /// Get the frames of an icon, falling back to the default icon if the theme lacks it.
+    pub fn frames(&mut self, icon: CursorIcon) -> &[CursorFrame] {
+        if !self.icons.contains_key(&icon) {
+            let frames = self.load_frames(icon);
+            if frames.is_empty() {
+                log::error!(target: "Cursor Theme","Cursor {:?} not found in the theme",icon);
+            }
+            self.icons.insert(icon, frames);
+        }
+        if self.icons[&icon].is_empty() && icon != CursorIcon::Default {
+            return self.frames(CursorIcon::Default);
+        }
+        &self.icons[&icon]
+    }
+
+    fn load_frames(&self, icon: CursorIcon) -> Vec<CursorFrame> {
+        let images = icon
+            .names()
+            .iter()
+            .find_map(|name| self.theme.load_icon(name))
+            .map(|path| {
+                let mut buffer = Vec::new();
+                std::fs::File::open(&path)
+                    .and_then(|mut file| file.read_to_end(&mut buffer))
+                    .ok()
+                    .map(|_| xcursor::parser::parse_xcursor(&buffer))
+                    .flatten()
+            })
+            .flatten()
+            .unwrap_or(Vec::new());
+
+        //Xcursor files contain the frames of every available size, only the closest one is kept.
+        let nominal_size = images
+            .iter()
+            .map(|image| image.size)
+            .min_by_key(|size| (*size as i64 - self.size as i64).abs());
+        images
+            .into_iter()
+            .filter(|image| Some(image.size) == nominal_size)
+            .map(|image| CursorFrame {
+                size: pal::Size2D::from((image.width, image.height)),
+                hotspot: pal::Offset2D {
+                    x: image.xhot as i32,
+                    y: image.yhot as i32,
+                },
+                delay: image.delay,
+                data: image.pixels_rgba,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Image currently drawn for a seat cursor, tracking its animation.
+pub struct DrawnCursor {
+    /// Drawn theme icon, `None` for client surfaces.
+    pub icon: Option<CursorIcon>,
+    pub frame: usize,
+    pub hotspot: pal::Offset2D<i32>,
+    pub size: pal::Size2D<u32>,
+    start: Instant,
+}
+impl DrawnCursor {
+    pub fn new(icon: Option<CursorIcon>) -> Self {
+        let frame = 0;
+        let hotspot = pal::Offset2D { x: 0, y: 0 };
+        let size = pal::Size2D::from((0, 0));
+        let start = Instant::now();
+        Self {
+            icon,
+            frame,
+            hotspot,
+            size,
+            start,
+        }
+    }
+
+    /// Get the index of the frame to show now and the time left before the following one,
+    /// that is `None` for static images.
+    pub fn current_frame(&self, frames: &[CursorFrame]) -> (usize, Option<Duration>) {
+        let total: u32 = frames.iter().map(|frame| frame.delay).sum();
+        if frames.len() < 2 || total == 0 {
+            return (0, None);
+        }
+        let mut elapsed = (self.start.elapsed().as_millis() % total as u128) as u32;
+        for (index, frame) in frames.iter().enumerate() {
+            if elapsed < frame.delay {
+                let left = Duration::from_millis((frame.delay - elapsed) as u64);
+                return (index, Some(left));
+            }
+            elapsed -= frame.delay;
+        }
+        (0, None)
+    }
+}
@@ -1,7 +1,7 @@
 //! Module containing platform events processing functions.
 
 use crate::geometry_manager::{
-    CursorRequest, KeyboardRequest, OutputRequest, SeatRequest, WCompRequest,
+    CursorImage, CursorRequest, KeyboardRequest, OutputRequest, SeatRequest, WCompRequest,
 };
 use crate::wcomp::WComp;
 use pal::PlatformBackend;
@@ -16,8 +16,12 @@ impl WComp {
             .flat_map(|request| {
                 match request {
                     pal::Event::Output { time: _, id, event } => match &event {
-                        pal::OutputEvent::Added(_) => {
+                        pal::OutputEvent::Added(output_info) => {
                             if self.platform.platform_type() == pal::PlatformType::Direct {
+                                //The surface of an output is created with the output id.
+                                let key: usize = id.into();
+                                self.pending_output_names
+                                    .insert(key, output_info.name.clone());
                                 self.platform
                                     .requests(vec![pal::definitions::Request::Surface {
                                         request: pal::definitions::SurfaceRequest::Create(Some(id)),
@@ -25,7 +29,11 @@ impl WComp {
                             }
                             Vec::new()
                         }
-                        pal::OutputEvent::Removed => Vec::new(),
+                        pal::OutputEvent::Removed => {
+                            let key: usize = id.into();
+                            self.pending_output_names.remove(&key);
+                            Vec::new()
+                        }
                         _ => Vec::new(),
                     },
                     pal::Event::Surface { time: _, id, event } => {
@@ -34,11 +42,22 @@ impl WComp {
                                 if let pal::definitions::Surface::WGpu(surface) =
                                     &surface_info.surface
                                 {
-                                    let id = id.into();
+                                    let id: usize = id.into();
+                                    //The name identifies the output in the layout profiles,
+                                    //surfaces not created for an output fall back to the runtime id.
+                                    let name = self
+                                        .pending_output_names
+                                        .remove(&id)
+                                        .unwrap_or(format!("Output {}", id));
                                     let size = surface_info.size.clone();
                                     let handle = surface.clone();
                                     let request = WCompRequest::Output {
-                                        request: OutputRequest::Added { id, handle, size },
+                                        request: OutputRequest::Added {
+                                            id,
+                                            name,
+                                            handle,
+                                            size,
+                                        },
                                     };
                                     vec![request]
                                 } else {
@@ -84,16 +103,9 @@ impl WComp {
                                 //self.messages.borrow_mut().push(WCompMessage::from(event));
                             }
                             pal::SeatEvent::Keyboard(pal::KeyboardEvent::Added(_keyboard_info)) => {
-                                //TODO Rimuovere valori hardcoded per il key rate and delay
                                 let id = id.into();
-                                let rate = 200;
-                                let delay = 25;
                                 let request = WCompRequest::Seat {
-                                    request: SeatRequest::Keyboard(KeyboardRequest::Added {
-                                        id,
-                                        rate,
-                                        delay,
-                                    }),
+                                    request: SeatRequest::Keyboard(KeyboardRequest::Added { id }),
                                 };
                                 vec![request]
                             }
@@ -124,12 +136,31 @@ impl WComp {
                                 vec![request]
                             }
                             pal::SeatEvent::Keyboard(pal::KeyboardEvent::AutoRepeat {
-                                rate: _,
-                                delay: _,
-                            }) => Vec::new(),
+                                rate,
+                                delay,
+                            }) => {
+                                let id = id.into();
+                                let request = WCompRequest::Seat {
+                                    request: SeatRequest::Keyboard(KeyboardRequest::RepeatInfo {
+                                        id,
+                                        rate: rate as i32,
+                                        delay: delay as i32,
+                                    }),
+                                };
+                                vec![request]
+                            }
                             pal::SeatEvent::Keyboard(pal::KeyboardEvent::LayoutModified {
-                                layout: _,
-                            }) => Vec::new(),
+                                layout,
+                            }) => {
+                                let id = id.into();
+                                let request = WCompRequest::Seat {
+                                    request: SeatRequest::Keyboard(KeyboardRequest::Layout {
+                                        id,
+                                        layout: layout.to_string(),
+                                    }),
+                                };
+                                vec![request]
+                            }
                             pal::SeatEvent::Cursor(pal::CursorEvent::Added(_info)) => {
                                 let size = pal::Size2D {
                                     width: 24,
@@ -137,7 +168,7 @@ impl WComp {
                                 };
                                 let (position, _depth) =
                                     self.geometry_manager.get_surface_optimal_position(&size);
-                                let image = None;
+                                let image = CursorImage::default();
 
                                 let id = id.into();
                                 let request = WCompRequest::Seat {
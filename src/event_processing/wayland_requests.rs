@@ -1,6 +1,9 @@
 //! Module containing wayland events processing functions.
 
-use crate::geometry_manager::{PopupState, SurfaceKind, SurfaceRequest, WCompRequest};
+use crate::geometry_manager::{
+    ConstraintKind, CursorImage, CursorRequest, PopupState, SeatRequest, SurfaceKind,
+    SurfaceRequest, WCompRequest,
+};
 use crate::wcomp::WComp;
 use ews::Buffer;
 
@@ -54,6 +57,8 @@ impl WComp {
                                 constraint_adjustment: positioner.constraint_adjustment,
                                 offset,
                                 reactive: positioner.reactive,
+                                bounds: None,
+                                geometry: None,
                             };
 
                             let kind = SurfaceKind::Popup{
@@ -90,17 +95,20 @@ impl WComp {
                     if let Some(seat_id) = ews::seat_id(&seat){
                         if let Some(cursor) = self.ews.get_cursor(seat_id){
                             cursor.grab_start_data().map(|mut start_data|{
-                                if let Some((surface,position)) = start_data.focus.as_mut() {
+                                let id = start_data.focus.as_mut().map(|(surface,position)|{
                                     let id = ews::with_states(&surface,|surface_data|ews::surface_id(&surface_data)).ok().flatten().unwrap();
                                     if let Some(surface) = self.geometry_manager.surface_ref(id) {
                                         position.x = surface.position().x;
                                         position.y = surface.position().y;
+                                        Some(id)
                                     }
-                                    else{log::error!(target: "WComp","Moving surface: surface {:#?} not found",surface);};
-                                }
+                                    else{log::error!(target: "WComp","Moving surface: surface {:#?} not found",surface);None}
+                                }).flatten();
 
-                                let move_logic = crate::move_logic::MoveLogic::new(start_data,self.async_requests.clone());
-                                cursor.set_grab(move_logic, serial);
+                                if let Some(id) = id {
+                                    let move_logic = crate::move_logic::MoveLogic::new(start_data,self.async_requests.clone(),seat_id,id,serial.into());
+                                    cursor.set_grab(move_logic, serial);
+                                }
                             });
                         }
                         else{log::error!(target: "WComp","Moving surface: cursor {:#?} not found",seat_id);};
@@ -117,7 +125,7 @@ impl WComp {
                                         position.x = surface.position().x;
                                         position.y = surface.position().y;
                                         surface.inner_geometry().map(|geometry|{
-                                            let move_logic = crate::resize_logic::ResizeLogic::new(start_data,self.async_requests.clone(),id,serial.into(),geometry.clone(),edges);
+                                            let move_logic = crate::resize_logic::ResizeLogic::new(start_data,self.async_requests.clone(),seat_id,id,serial.into(),geometry.clone(),edges);
                                             cursor.set_grab(move_logic, serial);
                                         });
                                     }
@@ -129,6 +137,29 @@ impl WComp {
                     }else{log::error!(target: "WComp","Resizing surface: cannot get id from cursor");};
                     Vec::new()
                 }
+                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Grab{surface,seat,serial}}=>{
+                    if let Some(seat_id) = ews::seat_id(&seat){
+                        if let Some(cursor) = self.ews.get_cursor(seat_id){
+                            let id = surface.get_surface().map(|raw_surface|ews::with_states(&raw_surface,|surface_data|ews::surface_id(&surface_data)).ok().flatten()).flatten();
+                            let parent = surface.get_parent_surface().map(|parent|ews::with_states(&parent,|surface_data|ews::surface_id(&surface_data)).ok().flatten()).flatten();
+                            id.map(|id|{
+                                //Nested popups keep the grabbing parents in the chain
+                                let mut popups = parent.map(|parent|self.geometry_manager.popup_grab_chain(seat_id,parent)).unwrap_or(Vec::new());
+                                popups.push(id);
+
+                                let start_data = cursor.grab_start_data().unwrap_or(ews::GrabStartData{
+                                    focus: None,
+                                    button: 0,
+                                    location: cursor.current_location(),
+                                });
+                                let popup_grab_logic = crate::popup_grab_logic::PopupGrabLogic::new(start_data,self.async_requests.clone(),seat_id,popups);
+                                cursor.set_grab(popup_grab_logic, serial);
+                                WCompRequest::Seat{request: SeatRequest::PopupGrabStart{id: seat_id,serial: serial.into(),surface: id,parent}}
+                            }).into_iter().collect::<Vec<_>>()
+                        }
+                        else{log::error!(target: "WComp","Grabbing popup: cursor {:#?} not found",seat_id);Vec::new()}
+                    }else{log::error!(target: "WComp","Grabbing popup: cannot get id from seat");Vec::new()}
+                }
                 ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Maximize{surface}}=>{
                     surface.get_surface().map(|raw_surface|{
                         ews::with_states(&raw_surface, |surface_data| {
@@ -145,29 +176,50 @@ impl WComp {
                         }).ok()
                     }).flatten().into_iter().collect::<Vec<_>>()
                 }
-                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Fullscreen{surface: _,output: _}}=>{
-                    Vec::new()
+                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Fullscreen{surface,output}}=>{
+                    let output = output.as_ref().map(|output|ews::output_id(output)).flatten();
+                    surface.get_surface().map(|raw_surface|{
+                        ews::with_states(&raw_surface, |surface_data| {
+                            let id = ews::surface_id(&surface_data).expect(&format!("{:#?} not found",surface));
+                            WCompRequest::Surface{request: SurfaceRequest::Fullscreen {id,output}}
+                        }).ok()
+                    }).flatten().into_iter().collect::<Vec<_>>()
                 }
-                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::UnFullscreen{surface: _}}=>{
-                    Vec::new()
+                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::UnFullscreen{surface}}=>{
+                    surface.get_surface().map(|raw_surface|{
+                        ews::with_states(&raw_surface, |surface_data| {
+                            let id = ews::surface_id(&surface_data).expect(&format!("{:#?} not found",surface));
+                            WCompRequest::Surface{request: SurfaceRequest::Unfullscreen {id}}
+                        }).ok()
+                    }).flatten().into_iter().collect::<Vec<_>>()
                 }
-                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Minimize{surface: _}}=>{
-                    println!("Minimize event detected!");
-                    Vec::new()
+                ews::WaylandRequest::XdgRequest{request: ews::XdgRequest::Minimize{surface}}=>{
+                    surface.get_surface().map(|raw_surface|{
+                        ews::with_states(&raw_surface, |surface_data| {
+                            let id = ews::surface_id(&surface_data).expect(&format!("{:#?} not found",surface));
+                            WCompRequest::Surface{request: SurfaceRequest::Minimize {id}}
+                        }).ok()
+                    }).flatten().into_iter().collect::<Vec<_>>()
+                }
+                ews::WaylandRequest::Commit {surface} if ews::get_role(&surface) == Some(ews::CURSOR_IMAGE_ROLE)=>{
+                    //Cursor surfaces are not managed, their buffer is read when the cursor gets drawn.
+                    let hotspot = crate::utils::cursor_hotspot(&surface);
+                    vec![WCompRequest::Seat{request: SeatRequest::Cursor(CursorRequest::SurfaceCommitted{handle: surface,hotspot})}]
                 }
                 ews::WaylandRequest::Commit {surface}=>{
                     ews::with_states(&surface,|surface_data|{
                         let id = ews::surface_id(&surface_data).expect(&format!("Id on {:#?} not found, it is likely a ews bug missing to track such surface",surface));
                         let mut events = Vec::new();
                         let mut attributes = surface_data.cached_state.current::<ews::SurfaceAttributes>();
+                        //Buffers are sized in pixels, the surface geometry is logical.
+                        let scale = attributes.buffer_scale.max(1) as u32;
                         match attributes.buffer.as_ref() {
                             Some(ews::BufferAssignment::NewBuffer{buffer,delta: _})=>{
                                 match ews::buffer_type(&buffer) {
                                     Some(ews::BufferType::Shm)=>{
                                         ews::with_buffer_contents(&buffer,|_data,info|{
-                                            let size = pal::Size2D::from((info.width as u32,info.height as u32));
-                                            let (position,_depth) = self.geometry_manager.get_surface_optimal_position(&size);
-                                            let geometry = pal::Rectangle::from((position,size.clone()));
+                                            let size = pal::Size2D::from((info.width as u32 / scale,info.height as u32 / scale));
+                                            let geometry = pal::Rectangle::from((pal::Position2D::from((0,0)),size.clone()));
                                             let inner_geometry = surface_data.cached_state.current::<ews::SurfaceCachedState>().geometry.map(|geometry|{
                                                 let size = pal::Size2D::from((geometry.size.w as u32,geometry.size.h as u32));
                                                 let position = pal::Position2D::from((geometry.loc.x as i32, geometry.loc.y as i32));
@@ -175,21 +227,20 @@ impl WComp {
                                             }).unwrap_or(geometry);
 
                                             let handle = buffer.clone();
-                                            events.push(WCompRequest::Surface{request: SurfaceRequest::AttachBuffer{id,handle,inner_geometry,size}});
+                                            events.push(WCompRequest::Surface{request: SurfaceRequest::AttachBuffer{id,handle,inner_geometry,size,scale}});
                                         }).unwrap();
                                     }
                                     Some(ews::BufferType::Dma)=>{
                                         buffer.as_ref().user_data().get::<ews::Dmabuf>().map(|dmabuf|{
-                                            let size = pal::Size2D::from((dmabuf.width() as u32,dmabuf.height() as u32));
-                                            let (position,_depth) = self.geometry_manager.get_surface_optimal_position(&size);
-                                            let geometry = pal::Rectangle::from((position,size.clone()));
+                                            let size = pal::Size2D::from((dmabuf.width() as u32 / scale,dmabuf.height() as u32 / scale));
+                                            let geometry = pal::Rectangle::from((pal::Position2D::from((0,0)),size.clone()));
                                             let inner_geometry = surface_data.cached_state.current::<ews::SurfaceCachedState>().geometry.map(|geometry|{
                                                 let size = pal::Size2D::from((geometry.size.w as u32,geometry.size.h as u32));
                                                 let position = pal::Position2D::from((geometry.loc.x as i32, geometry.loc.y as i32));
                                                 pal::Rectangle::from((position,size))
                                             }).unwrap_or(geometry);
                                             let handle = buffer.clone();
-                                            events.push(WCompRequest::Surface{request: SurfaceRequest::AttachBuffer{id,handle,inner_geometry,size}});
+                                            events.push(WCompRequest::Surface{request: SurfaceRequest::AttachBuffer{id,handle,inner_geometry,size,scale}});
                                         });
                                     }
                                     _=>unreachable!()
@@ -206,20 +257,27 @@ impl WComp {
                         events
                     }).unwrap_or(Vec::new())
                 },
-                ews::WaylandRequest::Seat{seat: _,request: ews::SeatRequest::CursorImage(image_status)}=>{
-                    match image_status {
-                        ews::CursorImageStatus::Image(_surface)=>{
-                            //println!("Cursor surface: {:#?}",surface);
-                        }
-                        ews::CursorImageStatus::Default=>{
-
-                        }
-                        ews::CursorImageStatus::Hidden=>{
-
-                        }
-                    }
-                    //println!("Seat request: {:#?}",request);
-                    Vec::new()
+                ews::WaylandRequest::Seat{seat,request: ews::SeatRequest::CursorImage(image_status)}=>{
+                    if let Some(id) = ews::seat_id(&seat){
+                        let image = match image_status {
+                            ews::CursorImageStatus::Image(surface)=>{
+                                let hotspot = crate::utils::cursor_hotspot(&surface);
+                                CursorImage::Surface{handle: surface,hotspot}
+                            }
+                            ews::CursorImageStatus::Default=>CursorImage::default(),
+                            ews::CursorImageStatus::Hidden=>CursorImage::Hidden
+                        };
+                        vec![WCompRequest::Seat{request: SeatRequest::Cursor(CursorRequest::Image{id,image})}]
+                    }else{log::error!(target: "WComp","Setting cursor image: cannot get id from seat");Vec::new()}
+                }
+                ews::WaylandRequest::Seat{seat,request: ews::SeatRequest::LockPointer{surface,region}}=>{
+                    self.pointer_constraint_request(&seat,&surface,Some((ConstraintKind::Lock,region)))
+                }
+                ews::WaylandRequest::Seat{seat,request: ews::SeatRequest::ConfinePointer{surface,region}}=>{
+                    self.pointer_constraint_request(&seat,&surface,Some((ConstraintKind::Confine,region)))
+                }
+                ews::WaylandRequest::Seat{seat,request: ews::SeatRequest::ReleasePointer{surface}}=>{
+                    self.pointer_constraint_request(&seat,&surface,None)
                 }
                 ews::WaylandRequest::Dmabuf{buffer: _}=>{
                     Vec::new()
@@ -238,4 +296,57 @@ impl WComp {
             }
         }).collect::<Vec<_>>().into_iter()
     }
+
+    /// Build the request setting or removing the pointer constraint of a surface.
+    fn pointer_constraint_request(
+        &self,
+        seat: &ews::Seat,
+        surface: &ews::WlSurface,
+        constraint: Option<(ConstraintKind, Option<ews::RegionAttributes>)>,
+    ) -> Vec<WCompRequest> {
+        let id = ews::seat_id(seat);
+        let surface = ews::with_states(surface, |surface_data| ews::surface_id(&surface_data))
+            .ok()
+            .flatten();
+        let (id, surface) = match (id, surface) {
+            (Some(id), Some(surface)) => (id, surface),
+            _ => {
+                log::error!(target: "WComp","Pointer constraint: cannot get id from seat or surface");
+                return Vec::new();
+            }
+        };
+        let request = match constraint {
+            Some((kind, region)) => {
+                //Subtracted rectangles are ignored, the region is the union of the added ones.
+                let region = region.map(|region| {
+                    region
+                        .rects
+                        .iter()
+                        .filter(|(rectangle_kind, _rectangle)| {
+                            *rectangle_kind == ews::RectangleKind::Add
+                        })
+                        .map(|(_rectangle_kind, rectangle)| {
+                            let position =
+                                pal::Position2D::from((rectangle.loc.x, rectangle.loc.y));
+                            let size = pal::Size2D::from((
+                                rectangle.size.w.max(0) as u32,
+                                rectangle.size.h.max(0) as u32,
+                            ));
+                            pal::Rectangle::from((position, size))
+                        })
+                        .collect()
+                });
+                CursorRequest::Constrain {
+                    id,
+                    surface,
+                    kind,
+                    region,
+                }
+            }
+            None => CursorRequest::Unconstrain { id, surface },
+        };
+        vec![WCompRequest::Seat {
+            request: SeatRequest::Cursor(request),
+        }]
+    }
 }
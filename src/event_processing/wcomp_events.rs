@@ -1,12 +1,17 @@
 //! Module containing wcomp events processing functions.
 
 use crate::geometry_manager::{
-    CursorEvent, KeyboardEvent, OutputEvent, SeatEvent, SurfaceEvent, SurfaceKind, WCompEvent,
+    CursorEvent, KeyboardEvent, KeyboardRequest, Keymap, OutputEvent, OutputTransform, SeatEvent,
+    SeatRequest, SurfaceEvent, SurfaceKind, SurfaceRequest, WCompEvent, WCompRequest,
 };
+use crate::keybindings::{Action, Modifiers};
 use crate::wcomp::WComp;
 use ews::Buffer;
 use screen_task::ScreenTask;
 
+/// Id of the screen task surface used to preview the tiling of a dragged surface.
+const TILING_PREVIEW_ID: usize = usize::MAX;
+
 impl WComp {
     /// Process [wcomp events][WCompEvent].
     pub fn process_events(&mut self, events: impl Iterator<Item = WCompEvent>) -> bool {
@@ -18,13 +23,21 @@ impl WComp {
                     event: OutputEvent::Added { id, handle, size },
                 } => {
                     log::info!(target: "WCompEvent","Output {} added of {:?}",id,size);
+                    //Outputs are advertised with the connector name their layout profiles are keyed on.
+                    let name = self
+                        .geometry_manager
+                        .output_ref(id)
+                        .map(|output| output.name.clone())
+                        .unwrap_or(format!("Output {}", id));
                     self.wgpu_engine.create_surface(
                         id.into(),
-                        String::from(format!("Output {}", id)),
+                        name.clone(),
                         handle,
                         size.width,
                         size.height,
                     );
+                    self.ews.create_output(id, name);
+                    self.advertise_output(id);
                     redraw = true;
                 }
                 WCompEvent::Output {
@@ -33,6 +46,7 @@ impl WComp {
                 } => {
                     log::info!(target: "WCompEvent","Output {} removed",id);
                     self.wgpu_engine.destroy_surface(id.into());
+                    self.ews.destroy_output(id);
                     redraw = true;
                 }
                 WCompEvent::Output {
@@ -41,19 +55,77 @@ impl WComp {
                 } => {
                     log::info!(target: "WCompEvent","Output {} resized to {:?}",id,size);
                     self.wgpu_engine.resize_surface(id, size.width, size.height);
+                    self.advertise_output(id);
                     redraw = true;
                 }
                 WCompEvent::Output {
                     serial: _,
-                    event: OutputEvent::Moved { id, position },
+                    event: OutputEvent::Moved { id, position, .. },
                 } => {
                     log::info!(target: "WCompEvent","Output {} moved to {:?}",id,position);
-                    self.wgpu_engine.task_handle_cast_mut(
-                        &self.screen_task,
-                        |screen_task: &mut ScreenTask| {
-                            screen_task.move_output(id, [position.x, position.y]);
-                        },
+                    let position = self
+                        .geometry_manager
+                        .output_ref(id)
+                        .map(|output| self.geometry_manager.output_physical_position(output));
+                    if let Some(position) = position {
+                        self.wgpu_engine.task_handle_cast_mut(
+                            &self.screen_task,
+                            |screen_task: &mut ScreenTask| {
+                                screen_task.move_output(id, [position.x, position.y]);
+                            },
+                        );
+                    }
+                    self.advertise_output(id);
+                    redraw = true;
+                }
+                WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Scaled { id, scale },
+                } => {
+                    log::info!(target: "WCompEvent","Output {} scaled to {}",id,scale);
+                    self.advertise_output(id);
+                    self.refresh_physical_geometry();
+                    redraw = true;
+                }
+                WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Enabled { id, handle, size },
+                } => {
+                    log::info!(target: "WCompEvent","Output {} enabled",id);
+                    //Outputs are advertised with the connector name their layout profiles are keyed on.
+                    let name = self
+                        .geometry_manager
+                        .output_ref(id)
+                        .map(|output| output.name.clone())
+                        .unwrap_or(format!("Output {}", id));
+                    self.wgpu_engine.create_surface(
+                        id.into(),
+                        name.clone(),
+                        handle,
+                        size.width,
+                        size.height,
                     );
+                    self.ews.create_output(id, name);
+                    self.advertise_output(id);
+                    redraw = true;
+                }
+                WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Disabled { id },
+                } => {
+                    log::info!(target: "WCompEvent","Output {} disabled",id);
+                    self.wgpu_engine.destroy_surface(id.into());
+                    self.ews.destroy_output(id);
+                    redraw = true;
+                }
+                WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Transformed { id, transform },
+                } => {
+                    log::info!(target: "WCompEvent","Output {} transformed to {:?}",id,transform);
+                    self.advertise_output(id);
+                    self.refresh_physical_geometry();
+                    redraw = true;
                 }
                 WCompEvent::Seat {
                     serial: _,
@@ -71,10 +143,84 @@ impl WComp {
                 }
                 WCompEvent::Seat {
                     serial: _,
-                    event: SeatEvent::Keyboard(KeyboardEvent::Added { id, rate, delay }),
+                    event: SeatEvent::PopupGrabStarted { id, surface },
+                } => {
+                    log::info!(target: "WCompEvent","Seat {} popup grab started on surface {}",id,surface);
+                }
+                WCompEvent::Seat {
+                    serial,
+                    event:
+                        SeatEvent::PopupGrabStopped {
+                            id,
+                            serial: grab_serial,
+                            surfaces,
+                            focus: _,
+                        },
+                } => {
+                    log::info!(target: "WCompEvent","Seat {} popup grab dismissed {:?}",id,surfaces);
+                    for surface_id in surfaces {
+                        self.geometry_manager
+                            .surface_ref(surface_id)
+                            .map(|surface| match surface.kind() {
+                                SurfaceKind::Popup { handle, .. } => handle.send_popup_done(),
+                                _ => (),
+                            });
+                    }
+                    //The pointer grab is released if the whole chain has been dismissed by the client.
+                    if self.geometry_manager.popup_grab_ref(id).is_none() {
+                        self.ews.get_cursor(id).map(|cursor| {
+                            if cursor.has_grab(grab_serial.into()) {
+                                cursor.unset_grab(serial.into(), 0);
+                            }
+                        });
+                    }
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event:
+                        SeatEvent::Keyboard(KeyboardEvent::Added {
+                            id,
+                            rate,
+                            delay,
+                            keymap,
+                        }),
+                } => {
+                    log::info!(target: "WCompEvent","Keyboard {} added with keymap {:?}",id,keymap);
+                    //Keyboards are created with the delay before the rate, as smithay's Seat::add_keyboard.
+                    self.ews
+                        .add_keyboard(id, xkb_config(&keymap), delay, rate);
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event: SeatEvent::Keyboard(KeyboardEvent::RepeatInfo { id, rate, delay }),
                 } => {
-                    log::info!(target: "WCompEvent","Keyboard {} added",id);
-                    self.ews.add_keyboard(id, rate, delay);
+                    log::info!(target: "WCompEvent","Keyboard {} repeat rate {} delay {}",id,rate,delay);
+                    self.ews.get_keyboard(id).map(|keyboard| {
+                        keyboard.change_repeat_info(rate, delay);
+                    });
+                }
+                WCompEvent::Seat {
+                    serial,
+                    event: SeatEvent::Keyboard(KeyboardEvent::Keymap { id, keymap }),
+                } => {
+                    log::info!(target: "WCompEvent","Keyboard {} keymap {:?}",id,keymap);
+                    //The keymap is compiled when the keyboard is created, so it gets recreated.
+                    let keyboard = self.geometry_manager.keyboard_ref(id).map(|keyboard| {
+                        (keyboard.rate(), keyboard.delay(), keyboard.focus().clone())
+                    });
+                    if let Some((rate, delay, focus)) = keyboard {
+                        self.ews.del_keyboard(id);
+                        self.ews
+                            .add_keyboard(id, xkb_config(&keymap), delay, rate);
+                        let handle = focus
+                            .map(|focus| self.geometry_manager.surface_ref(focus))
+                            .flatten()
+                            .map(|surface| surface.handle())
+                            .flatten();
+                        self.ews.get_keyboard(id).map(|keyboard| {
+                            keyboard.set_focus(handle, serial.into());
+                        });
+                    }
                 }
                 WCompEvent::Seat {
                     serial: _,
@@ -112,19 +258,48 @@ impl WComp {
                         }),
                 } => {
                     log::info!(target: "WCompEvent","Keyboard {} key {:?}",id,key);
-                    self.ews.get_keyboard(id).map(|keyboard| {
-                        let keystate = match state {
-                            pal::State::Down => ews::KeyState::Pressed,
-                            pal::State::Up => ews::KeyState::Released,
-                        };
-                        keyboard.input::<(), _>(
-                            code,
-                            keystate,
-                            serial.into(),
-                            time,
-                            |_modifier, _keysim| ews::FilterResult::Forward,
-                        );
-                    });
+                    let keystate = match state {
+                        pal::State::Down => ews::KeyState::Pressed,
+                        pal::State::Up => ews::KeyState::Released,
+                    };
+                    //The release of an intercepted press is swallowed even if the modifiers changed meanwhile.
+                    let swallow =
+                        keystate == ews::KeyState::Released && self.keybindings.release(id, code);
+                    let keybindings = &self.keybindings;
+                    let action = self
+                        .ews
+                        .get_keyboard(id)
+                        .map(|keyboard| {
+                            keyboard.input::<Option<Action>, _>(
+                                code,
+                                keystate,
+                                serial.into(),
+                                time,
+                                |modifiers, keysym| {
+                                    if swallow {
+                                        return ews::FilterResult::Intercept(None);
+                                    }
+                                    if keystate == ews::KeyState::Released {
+                                        return ews::FilterResult::Forward;
+                                    }
+                                    let modifiers = Modifiers::from(modifiers);
+                                    keysym
+                                        .raw_syms()
+                                        .iter()
+                                        .find_map(|sym| keybindings.action(modifiers, *sym))
+                                        .map(|action| {
+                                            ews::FilterResult::Intercept(Some(action.clone()))
+                                        })
+                                        .unwrap_or(ews::FilterResult::Forward)
+                                },
+                            )
+                        })
+                        .flatten()
+                        .flatten();
+                    if let Some(action) = action {
+                        self.keybindings.intercept(id, code);
+                        self.run_action(id, action);
+                    }
                 }
                 WCompEvent::Seat {
                     serial: _,
@@ -137,6 +312,8 @@ impl WComp {
                 } => {
                     log::info!(target: "WCompEvent","Cursor {} added",id);
                     self.ews.add_cursor(id);
+                    self.draw_cursor(id, true);
+                    redraw = true;
                 }
                 WCompEvent::Seat {
                     serial: _,
@@ -144,6 +321,44 @@ impl WComp {
                 } => {
                     log::info!(target: "WCompEvent","Cursor {} removed",id);
                     self.ews.del_cursor(id);
+                    self.remove_cursor_image(id);
+                    redraw = true;
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event: SeatEvent::Cursor(CursorEvent::Image { id, image }),
+                } => {
+                    log::info!(target: "WCompEvent","Cursor {} image {:?}",id,image);
+                    self.draw_cursor(id, true);
+                    redraw = true;
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event: SeatEvent::Cursor(CursorEvent::ConstraintActivated { id, surface, kind }),
+                } => {
+                    log::info!(target: "WCompEvent","Cursor {} constraint {:?} of surface {} activated",id,kind,surface);
+                    let handle = self
+                        .geometry_manager
+                        .surface_ref(surface)
+                        .map(|surface| surface.handle())
+                        .flatten();
+                    if let (Some(cursor), Some(handle)) = (self.ews.get_cursor(id), handle) {
+                        cursor.set_constraint_active(handle, true);
+                    }
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event: SeatEvent::Cursor(CursorEvent::ConstraintDeactivated { id, surface }),
+                } => {
+                    log::info!(target: "WCompEvent","Cursor {} constraint of surface {} deactivated",id,surface);
+                    let handle = self
+                        .geometry_manager
+                        .surface_ref(surface)
+                        .map(|surface| surface.handle())
+                        .flatten();
+                    if let (Some(cursor), Some(handle)) = (self.ews.get_cursor(id), handle) {
+                        cursor.set_constraint_active(handle, false);
+                    }
                 }
                 WCompEvent::Seat {
                     serial,
@@ -174,6 +389,7 @@ impl WComp {
                     } else {
                         log::error!(target: "WCompEvent","Seat {} not found to get cursor",id);
                     }
+                    self.draw_cursor(id, false);
                     redraw = true;
                 }
                 WCompEvent::Seat {
@@ -270,14 +486,45 @@ impl WComp {
                         },
                 } => {
                     log::info!(target: "WCompEvent","Surface {} moved to {}:{}",id,position,depth);
+                    if !self.geometry_manager.is_surface_visible(id) {
+                        return;
+                    }
+                    //Moving to an output with a different scale changes the physical size too.
+                    let size = self
+                        .geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| surface.size())
+                        .flatten();
+                    let geometry = self.geometry_manager.to_physical(&pal::Rectangle::from((
+                        position,
+                        size.clone().unwrap_or(pal::Size2D::from((0, 0))),
+                    )));
                     self.wgpu_engine.task_handle_cast_mut(
                         &self.screen_task,
                         |screen_task: &mut ScreenTask| {
-                            screen_task.move_surface(id, [position.x, position.y, depth as i32]);
+                            screen_task.move_surface(
+                                id,
+                                [geometry.position.x, geometry.position.y, depth as i32],
+                            );
+                            if size.is_some() {
+                                screen_task.resize_surface(id, geometry.size.clone().into());
+                            }
                         },
                     );
                     redraw = true;
                 }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::InteractiveMoveStarted {id,serial:_}
+                } => {
+                    log::info!(target: "WCompEvent","Interactive move started on surface {}",id);
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::InteractiveMoveStopped {id,serial:_}
+                } => {
+                    log::info!(target: "WCompEvent","Interactive move stopped on surface {}",id);
+                }
                 WCompEvent::Surface {
                     serial: _,
                     event: SurfaceEvent::InteractiveResizeStarted {id,serial:_,edge}
@@ -321,6 +568,18 @@ impl WComp {
                     event: SurfaceEvent::Resized { id, size },
                 } => {
                     log::info!(target: "WCompEvent","Resizing surface {:#?} to {}",id,size);
+                    if !self.geometry_manager.is_surface_visible(id) {
+                        return;
+                    }
+                    let position = self
+                        .geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| surface.position().clone())
+                        .unwrap_or(pal::Position2D::from((0, 0)));
+                    let size = self
+                        .geometry_manager
+                        .to_physical(&pal::Rectangle::from((position, size)))
+                        .size;
                     self.wgpu_engine.task_handle_cast_mut(
                         &self.screen_task,
                         |screen_task: &mut ScreenTask| {
@@ -363,6 +622,10 @@ impl WComp {
                         },
                 } => {
                     log::info!(target: "WCompEvent","Attaching buffer to surface {}",id);
+                    if !self.geometry_manager.is_surface_visible(id) {
+                        return;
+                    }
+                    let geometry = self.geometry_manager.to_physical(&geometry);
                     match ews::buffer_type(&handle) {
                         Some(ews::BufferType::Shm) => {
                             ews::with_buffer_contents(&handle, |data, info| {
@@ -428,6 +691,9 @@ impl WComp {
                         },
                 } => {
                     log::info!(target: "WCompEvent","Replacing buffer of surface {}",id);
+                    if !self.geometry_manager.is_surface_visible(id) {
+                        return;
+                    }
                     let source = match ews::buffer_type(&handle) {
                         Some(ews::BufferType::Shm) => {
                             ews::with_buffer_contents(&handle, |data, info| {
@@ -518,11 +784,174 @@ impl WComp {
                             _ => (),
                         });
                 }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Minimized { id },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} minimized",id);
+                    let ids = self
+                        .geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| {
+                            surface
+                                .surfaces_ref()
+                                .filter(|surface| surface.buffer().is_some())
+                                .map(|surface| surface.id())
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or(Vec::new());
+                    self.wgpu_engine.task_handle_cast_mut(
+                        &self.screen_task,
+                        |screen_task: &mut ScreenTask| {
+                            ids.into_iter().for_each(|id| screen_task.remove_surface(id));
+                        },
+                    );
+                    redraw = true;
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Restored { id },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} restored",id);
+                    redraw = true;
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::PopupConfiguration { id, geometry },
+                } => {
+                    log::info!(target: "WCompEvent","Popup {} configured with geometry {:?}",id,geometry);
+                    self.geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| match surface.kind() {
+                            SurfaceKind::Popup { handle, .. } => {
+                                handle
+                                    .with_pending_state(|popup_state| {
+                                        popup_state.geometry = ews::Rectangle::from_loc_and_size(
+                                            (geometry.position.x, geometry.position.y),
+                                            (geometry.size.width as i32, geometry.size.height as i32),
+                                        );
+                                    })
+                                    .ok()
+                                    .map(|_| handle.send_configure().ok());
+                            }
+                            _ => (),
+                        });
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Fullscreened { id, output },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} fullscreened on output {:?}",id,output);
+                    self.geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| match surface.kind() {
+                            SurfaceKind::Toplevel { handle, .. } => {
+                                handle
+                                    .with_pending_state(|top_level_state| {
+                                        top_level_state.states.set(ews::SurfaceState::Fullscreen);
+                                    })
+                                    .unwrap();
+                                handle.send_configure();
+                            }
+                            _ => (),
+                        });
+                    redraw = true;
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Unfullscreened { id },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} unfullscreened",id);
+                    self.geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| match surface.kind() {
+                            SurfaceKind::Toplevel { handle, .. } => {
+                                handle
+                                    .with_pending_state(|top_level_state| {
+                                        top_level_state.states.unset(ews::SurfaceState::Fullscreen);
+                                    })
+                                    .unwrap();
+                                handle.send_configure();
+                            }
+                            _ => (),
+                        });
+                    redraw = true;
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Tiled { id, zone },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} tiled to {:?}",id,zone);
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Untiled { id },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} untiled",id);
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::TilingPreview { id, geometry },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} tiling preview {:?}",id,geometry);
+                    let geometry = geometry.map(|geometry| self.geometry_manager.to_physical(&geometry));
+                    let depth = self.geometry_manager.preview_depth() as i32;
+                    self.wgpu_engine.task_handle_cast_mut(
+                        &self.screen_task,
+                        |screen_task: &mut ScreenTask| match &geometry {
+                            Some(geometry) => {
+                                let source = crate::utils::outline_source(&geometry.size);
+                                screen_task.create_surface(
+                                    TILING_PREVIEW_ID,
+                                    "",
+                                    source,
+                                    [
+                                        geometry.position.x,
+                                        geometry.position.y,
+                                        depth,
+                                    ],
+                                    geometry.size.clone().into(),
+                                );
+                            }
+                            None => screen_task.remove_surface(TILING_PREVIEW_ID),
+                        },
+                    );
+                    redraw = true;
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::OutputEntered { id, output },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} entered output {}",id,output);
+                    let handle = self
+                        .geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| surface.handle())
+                        .flatten();
+                    if let (Some(handle), Some(output)) = (handle, self.ews.get_output(output)) {
+                        output.enter(handle);
+                    }
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::OutputLeft { id, output },
+                } => {
+                    log::info!(target: "WCompEvent","Surface {} left output {}",id,output);
+                    let handle = self
+                        .geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| surface.handle())
+                        .flatten();
+                    if let (Some(handle), Some(output)) = (handle, self.ews.get_output(output)) {
+                        output.leave(handle);
+                    }
+                }
                 WCompEvent::Surface {
                     serial: _,
                     event: SurfaceEvent::Committed { id },
                 } => {
                     log::info!(target: "WCompEvent","Surface {} committed",id);
+                    let visible = self.geometry_manager.is_surface_visible(id);
                     self.geometry_manager
                         .surface_ref(id)
                         .map(|surface| (surface.handle().cloned(), surface.buffer().cloned()))
@@ -538,7 +967,7 @@ impl WComp {
                                         buffer.as_ref().map(|buffer| {
                                             let buffer_type = ews::buffer_type(&buffer);
                                             match buffer_type {
-                                                Some(ews::BufferType::Shm) => {
+                                                Some(ews::BufferType::Shm) if visible => {
                                                     ews::with_buffer_contents(
                                                         &buffer,
                                                         |data, _info| {
@@ -574,4 +1003,155 @@ impl WComp {
         });
         redraw
     }
+
+    /// Perform the action of a keybinding triggered on a seat.
+    fn run_action(&mut self, seat: usize, action: Action) {
+        log::info!(target: "WCompEvent","Seat {} keybinding action {:?}",seat,action);
+        let request = match action {
+            Action::Close => {
+                let active = self
+                    .geometry_manager
+                    .active_surface()
+                    .map(|active| self.geometry_manager.surface_ref(active))
+                    .flatten();
+                if let Some(SurfaceKind::Toplevel { handle, .. }) =
+                    active.map(|surface| surface.kind())
+                {
+                    handle.send_close();
+                }
+                None
+            }
+            Action::Maximize => self
+                .geometry_manager
+                .active_surface()
+                .map(|id| {
+                    self.geometry_manager
+                        .surface_ref(id)
+                        .map(|surface| surface.state())
+                        .flatten()
+                        .map(|state| match state.is_maximized() {
+                            true => SurfaceRequest::Unmaximize { id },
+                            false => SurfaceRequest::Maximize { id },
+                        })
+                })
+                .flatten()
+                .map(|request| WCompRequest::Surface { request }),
+            Action::FocusNext => Some(WCompRequest::Seat {
+                request: SeatRequest::Keyboard(KeyboardRequest::FocusNext { id: seat }),
+            }),
+            Action::FocusPrevious => Some(WCompRequest::Seat {
+                request: SeatRequest::Keyboard(KeyboardRequest::FocusPrevious { id: seat }),
+            }),
+            Action::SwitchOutput => Some(WCompRequest::Seat {
+                request: SeatRequest::SwitchOutput { id: seat },
+            }),
+            Action::Spawn(command) => {
+                //The command is backgrounded by the shell, so only the shell is left to reap.
+                //It is waited on its own thread, not to stall the event loop.
+                let result = std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(format!("{} &", command))
+                    .spawn();
+                match result {
+                    Ok(mut child) => {
+                        std::thread::spawn(move || child.wait());
+                    }
+                    Err(error) => {
+                        log::error!(target: "WCompEvent","Failed to spawn {:?}: {}",command,error);
+                    }
+                }
+                None
+            }
+        };
+        if let Some(request) = request {
+            self.async_requests.borrow_mut().push(request);
+        }
+    }
+
+    /// Advertise the current mode, position and scale of an output to the clients.
+    fn advertise_output(&self, id: usize) {
+        let output = self.geometry_manager.output_ref(id);
+        if let (Some(output), Some(handle)) = (output, self.ews.get_output(id)) {
+            let mode = ews::Mode {
+                size: (
+                    output.physical_size.width as i32,
+                    output.physical_size.height as i32,
+                )
+                    .into(),
+                refresh: 60_000,
+            };
+            let position: (i32, i32) = output.geometry.position.clone().into();
+            //wl_output only supports integer scales: fractionally scaled outputs ask clients
+            //for the next integer scale and their buffers get downscaled.
+            let scale = output.scale.ceil() as i32;
+            let transform = match output.transform {
+                OutputTransform::Normal => ews::Transform::Normal,
+                OutputTransform::Rotated90 => ews::Transform::_90,
+                OutputTransform::Rotated180 => ews::Transform::_180,
+                OutputTransform::Rotated270 => ews::Transform::_270,
+                OutputTransform::Flipped => ews::Transform::Flipped,
+                OutputTransform::Flipped90 => ews::Transform::Flipped90,
+                OutputTransform::Flipped180 => ews::Transform::Flipped180,
+                OutputTransform::Flipped270 => ews::Transform::Flipped270,
+            };
+            handle.change_current_state(
+                Some(mode),
+                Some(transform),
+                Some(scale),
+                Some(position.into()),
+            );
+            handle.set_preferred(mode);
+        }
+    }
+
+    /// Send again the physical geometry of all the outputs and visible surfaces to the renderer,
+    /// needed when a scale change alters the physical layout.
+    fn refresh_physical_geometry(&mut self) {
+        let outputs = self
+            .geometry_manager
+            .outputs_ref()
+            .map(|output| {
+                (
+                    output.id,
+                    self.geometry_manager.output_physical_position(output),
+                )
+            })
+            .collect::<Vec<_>>();
+        let surfaces = self
+            .geometry_manager
+            .surfaces_ref()
+            .filter(|surface| self.geometry_manager.is_surface_visible(surface.id()))
+            .filter_map(|surface| {
+                surface.geometry().map(|geometry| {
+                    (
+                        surface.id(),
+                        self.geometry_manager.to_physical(&geometry),
+                        surface.depth(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        self.wgpu_engine
+            .task_handle_cast_mut(&self.screen_task, |screen_task: &mut ScreenTask| {
+                for (id, position) in outputs {
+                    screen_task.move_output(id, [position.x, position.y]);
+                }
+                for (id, geometry, depth) in surfaces {
+                    screen_task
+                        .move_surface(id, [geometry.position.x, geometry.position.y, depth as i32]);
+                    screen_task.resize_surface(id, geometry.size.into());
+                }
+            });
+    }
+}
+
+/// Convert a keymap into the XKB names expected by the wayland server.
+fn xkb_config(keymap: &Keymap) -> ews::XkbConfig<'_> {
+    ews::XkbConfig {
+        rules: &keymap.rules,
+        model: &keymap.model,
+        layout: &keymap.layout,
+        variant: &keymap.variant,
+        options: Some(keymap.options.clone()).filter(|options| !options.is_empty()),
+    }
 }
@@ -16,12 +16,12 @@ impl WComp {
             .into_iter()
             .flat_map(|request| match request {
                 WCompRequest::Output {
-                    request: OutputRequest::Added { id, handle, size },
+                    request: OutputRequest::Added { id, name, handle, size },
                 } => {
                     log::info!(target: "WCompRequest","Output {} added",id);
                     self
                     .geometry_manager
-                    .add_output(id, handle, size)
+                    .add_output(id, name, handle, size)
                     .collect::<Vec<_>>()
                 },
                 WCompRequest::Output {
@@ -40,10 +40,43 @@ impl WComp {
                     .collect::<Vec<_>>()
                 },
                 WCompRequest::Output {
-                    request: OutputRequest::Moved { old: _, new_position: _ },
+                    request: OutputRequest::Moved { id, position },
                 } => {
-                    //log::info!(target: "WCompRequest","Output {} moved to {:?}",id,new_position);
-                    Vec::new()
+                    log::info!(target: "WCompRequest","Output {} moved to {:?}",id,position);
+                    self
+                    .geometry_manager
+                    .move_output(id, position)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Output {
+                    request: OutputRequest::Scaled { id, scale },
+                } => {
+                    log::info!(target: "WCompRequest","Output {} scaled to {}",id,scale);
+                    self
+                    .geometry_manager
+                    .scale_output(id, scale)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Output {
+                    request: OutputRequest::Transformed { id, transform },
+                } => {
+                    log::info!(target: "WCompRequest","Output {} transformed to {:?}",id,transform);
+                    self
+                    .geometry_manager
+                    .transform_output(id, transform)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Output {
+                    request: OutputRequest::Enabled { id },
+                } => {
+                    log::info!(target: "WCompRequest","Output {} enabled",id);
+                    self.geometry_manager.enable_output(id).collect::<Vec<_>>()
+                },
+                WCompRequest::Output {
+                    request: OutputRequest::Disabled { id },
+                } => {
+                    log::info!(target: "WCompRequest","Output {} disabled",id);
+                    self.geometry_manager.disable_output(id).collect::<Vec<_>>()
                 },
                 WCompRequest::Seat {
                     request: SeatRequest::Added { id, name },
@@ -57,6 +90,21 @@ impl WComp {
                     log::info!(target: "WCompRequest","Seat {} removed",id);
                     self.geometry_manager.del_seat(id).collect::<Vec<_>>()
                 },
+                WCompRequest::Seat {
+                    request: SeatRequest::PopupGrabStart { id, serial, surface, parent },
+                } => {
+                    log::info!(target: "WCompRequest","Seat {} popup grab started on surface {}",id,surface);
+                    self
+                    .geometry_manager
+                    .popup_grab_start(id, serial, surface, parent)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::PopupGrabStop { id },
+                } => {
+                    log::info!(target: "WCompRequest","Seat {} popup grab stopped",id);
+                    self.geometry_manager.popup_grab_stop(id).collect::<Vec<_>>()
+                },
                 WCompRequest::Seat {
                     request:
                         SeatRequest::Cursor(CursorRequest::Added {
@@ -78,12 +126,45 @@ impl WComp {
                     self.geometry_manager.del_cursor(id).collect::<Vec<_>>()
                 },
                 WCompRequest::Seat {
-                    request: SeatRequest::Keyboard(KeyboardRequest::Added { id, rate, delay }),
+                    request: SeatRequest::Cursor(CursorRequest::Image { id, image }),
+                } => {
+                    log::info!(target: "WCompRequest","Cursor on seat {} image {:?}",id,image);
+                    self
+                    .geometry_manager
+                    .set_cursor_image(id, image)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Cursor(CursorRequest::SurfaceCommitted { handle, hotspot }),
+                } => {
+                    log::info!(target: "WCompRequest","Cursor surface {:?} committed",handle);
+                    self
+                    .geometry_manager
+                    .commit_cursor_surface(handle, hotspot)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Keyboard(KeyboardRequest::Added { id }),
                 } => {
                     log::info!(target: "WCompRequest","Keyboard on seat {} added",id);
+                    self.geometry_manager.add_keyboard(id).collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Keyboard(KeyboardRequest::RepeatInfo { id, rate, delay }),
+                } => {
+                    log::info!(target: "WCompRequest","Keyboard on seat {} repeat rate {} delay {}",id,rate,delay);
+                    self
+                    .geometry_manager
+                    .keyboard_repeat_info(id, rate, delay)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Keyboard(KeyboardRequest::Layout { id, layout }),
+                } => {
+                    log::info!(target: "WCompRequest","Keyboard on seat {} layout {}",id,layout);
                     self
                     .geometry_manager
-                    .add_keyboard(id, rate, delay)
+                    .keyboard_layout(id, layout)
                     .collect::<Vec<_>>()
                 },
                 WCompRequest::Seat {
@@ -108,6 +189,54 @@ impl WComp {
                     .keyboard_key(id, time, code, key, state)
                     .collect::<Vec<_>>()
                 },
+                WCompRequest::Seat {
+                    request: SeatRequest::Keyboard(KeyboardRequest::FocusNext { id }),
+                } => {
+                    log::info!(target: "WCompRequest","Keyboard on seat {} focus next",id);
+                    self
+                    .geometry_manager
+                    .cycle_keyboard_focus(id, true)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Keyboard(KeyboardRequest::FocusPrevious { id }),
+                } => {
+                    log::info!(target: "WCompRequest","Keyboard on seat {} focus previous",id);
+                    self
+                    .geometry_manager
+                    .cycle_keyboard_focus(id, false)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::SwitchOutput { id },
+                } => {
+                    log::info!(target: "WCompRequest","Seat {} switching output",id);
+                    self.geometry_manager.switch_output(id).collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request:
+                        SeatRequest::Cursor(CursorRequest::Constrain {
+                            id,
+                            surface,
+                            kind,
+                            region,
+                        }),
+                } => {
+                    log::info!(target: "WCompRequest","Cursor on seat {} constrained by surface {} with {:?}",id,surface,kind);
+                    self
+                    .geometry_manager
+                    .constrain_cursor(id, surface, kind, region)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Seat {
+                    request: SeatRequest::Cursor(CursorRequest::Unconstrain { id, surface }),
+                } => {
+                    log::info!(target: "WCompRequest","Cursor on seat {} unconstrained by surface {}",id,surface);
+                    self
+                    .geometry_manager
+                    .unconstrain_cursor(id, surface)
+                    .collect::<Vec<_>>()
+                },
                 WCompRequest::Seat {
                     request: SeatRequest::Cursor(CursorRequest::Moved { id, position }),
                 } => {
@@ -209,6 +338,33 @@ impl WComp {
                     .resize_surface(id, size)
                     .collect::<Vec<_>>()
                 },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::InteractiveMoveStart { id,serial },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} interactive move started",id);
+                    self
+                    .geometry_manager
+                    .interactive_move_start(id,serial)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::InteractiveMove { id, serial, position, cursor },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} interactive move to {:?}",id,position);
+                    self
+                    .geometry_manager
+                    .interactive_move_surface(id, serial, position, cursor)
+                    .collect::<Vec<_>>()
+                },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::InteractiveMoveStop { id,serial },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} interactive move stopped",id);
+                    self
+                    .geometry_manager
+                    .interactive_move_end(id,serial)
+                    .collect::<Vec<_>>()
+                },
                 WCompRequest::Surface {
                     request: SurfaceRequest::InteractiveResizeStart { id,serial,edge },
                 } => {
@@ -258,12 +414,13 @@ impl WComp {
                             handle,
                             inner_geometry,
                             size,
+                            scale,
                         },
                 } => {
                     log::info!(target: "WCompRequest","Surface {} buffer attached",id);
                     self
                     .geometry_manager
-                    .attach_buffer(id, handle, inner_geometry, size)
+                    .attach_buffer(id, handle, inner_geometry, size, scale)
                     .collect::<Vec<_>>()
                 },
                 WCompRequest::Surface {
@@ -284,6 +441,30 @@ impl WComp {
                     log::info!(target: "WCompRequest","Surface {} unmaximized",id);
                     self.geometry_manager.unmaximize_surface(id).collect::<Vec<_>>()
                 },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::Minimize { id },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} minimized",id);
+                    self.geometry_manager.minimize_surface(id).collect::<Vec<_>>()
+                },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::Restore { id },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} restored",id);
+                    self.geometry_manager.restore_surface(id).collect::<Vec<_>>()
+                },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::Fullscreen { id, output },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} fullscreened on output {:?}",id,output);
+                    self.geometry_manager.fullscreen_surface(id, output).collect::<Vec<_>>()
+                },
+                WCompRequest::Surface {
+                    request: SurfaceRequest::Unfullscreen { id },
+                } => {
+                    log::info!(target: "WCompRequest","Surface {} unfullscreened",id);
+                    self.geometry_manager.unfullscreen_surface(id).collect::<Vec<_>>()
+                },
                 WCompRequest::Surface {
                     request: SurfaceRequest::Commit { id },
                 } => {
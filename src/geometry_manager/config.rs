@@ -0,0 +1,181 @@
+//! [Config][Config] related structures.
+//!
+//! Every config file lives in `$XDG_CONFIG_HOME/wcomp`, falling back to `~/.config/wcomp`.
+//! The general settings are read from `wcomp.conf`, one setting per line:
+//! ```text
+//! focus_policy = sloppy
+//! click_raise = false
+//! placement = smart
+//! snap_threshold = 16
+//! tiling_threshold = 32
+//! cursor_theme = Adwaita
+//! cursor_size = 32
+//! ```
+
+use super::{FocusPolicy, KeyboardConfig, OutputProfiles, PlacementStrategy};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// General settings of the compositor, unset fields keep the defaults.
+pub struct Settings {
+    pub focus_policy: Option<FocusPolicy>,
+    pub click_raise: Option<bool>,
+    pub placement_strategy: Option<PlacementStrategy>,
+    pub snap_threshold: Option<u32>,
+    pub tiling_threshold: Option<u32>,
+    /// Cursor theme, overriding `XCURSOR_THEME`.
+    pub cursor_theme: Option<String>,
+    /// Cursor size, overriding `XCURSOR_SIZE`.
+    pub cursor_size: Option<u32>,
+}
+impl Settings {
+    /// Load the settings from `path`, a missing file is considered empty.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        path.as_ref()
+            .map(|path| match std::fs::read_to_string(path) {
+                Ok(content) => Self::parse(&content),
+                Err(error) => {
+                    if error.kind() != std::io::ErrorKind::NotFound {
+                        log::error!(target: "Config","Failed to read {:?}: {}",path,error);
+                    }
+                    Self::default()
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut settings = Self::default();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line
+                .split_once('=')
+                .map(|(key, value)| settings.set(key.trim(), value.trim()))
+                .flatten();
+            if parsed.is_none() {
+                log::error!(target: "Config","Invalid line {} ignored: {}",index + 1,line)
+            }
+        }
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "focus_policy" => {
+                self.focus_policy = Some(match value {
+                    "click" => FocusPolicy::Click,
+                    "follows-mouse" => FocusPolicy::FollowsMouse,
+                    "sloppy" => FocusPolicy::Sloppy,
+                    _ => return None,
+                })
+            }
+            "click_raise" => self.click_raise = Some(value.parse().ok()?),
+            "placement" => {
+                self.placement_strategy = Some(match value {
+                    "centered" => PlacementStrategy::Centered,
+                    "cascade" => PlacementStrategy::Cascade,
+                    "under-cursor" => PlacementStrategy::UnderCursor,
+                    "smart" => PlacementStrategy::Smart,
+                    _ => return None,
+                })
+            }
+            "snap_threshold" => self.snap_threshold = Some(value.parse().ok()?),
+            "tiling_threshold" => self.tiling_threshold = Some(value.parse().ok()?),
+            "cursor_theme" => self.cursor_theme = Some(value.to_string()),
+            "cursor_size" => self.cursor_size = Some(value.parse().ok()?),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+#[derive(Debug)]
+/// Configuration of the compositor, gathered from all its config files.
+pub struct Config {
+    pub keyboards: KeyboardConfig,
+    pub output_profiles: OutputProfiles,
+    pub settings: Settings,
+}
+impl Config {
+    /// Load every config file, missing files are considered empty.
+    pub fn load() -> Self {
+        let keyboards = KeyboardConfig::load(Self::path("keyboards.conf"));
+        let output_profiles = OutputProfiles::load(Self::path("outputs.conf"));
+        let settings = Settings::load(Self::path("wcomp.conf"));
+        Self {
+            keyboards,
+            output_profiles,
+            settings,
+        }
+    }
+
+    /// Get the location of a config file, `None` if neither `XDG_CONFIG_HOME` nor `HOME` are set.
+    pub fn path(file: &str) -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("wcomp").join(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let content = "
+# General settings
+focus_policy = sloppy
+click_raise = false
+placement = under-cursor
+snap_threshold = 16
+tiling_threshold = 32
+cursor_theme = Adwaita
+cursor_size = 32
+";
+        assert_eq!(
+            Settings::parse(content),
+            Settings {
+                focus_policy: Some(FocusPolicy::Sloppy),
+                click_raise: Some(false),
+                placement_strategy: Some(PlacementStrategy::UnderCursor),
+                snap_threshold: Some(16),
+                tiling_threshold: Some(32),
+                cursor_theme: Some(String::from("Adwaita")),
+                cursor_size: Some(32),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_ignores_invalid_lines() {
+        let content = "
+focus_policy = hover
+click_raise = maybe
+snap_threshold = -1
+unknown = 1
+placement
+focus_policy=follows-mouse
+";
+        assert_eq!(
+            Settings::parse(content),
+            Settings {
+                focus_policy: Some(FocusPolicy::FollowsMouse),
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        assert_eq!(Settings::load(None), Settings::default());
+        assert_eq!(
+            Settings::load(Some(PathBuf::from("/nonexistent/wcomp.conf"))),
+            Settings::default()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! [KeyboardConfig][KeyboardConfig] related structures.
+//!
+//! Keyboards are configured in a plain text file, one section per seat name,
+//! the `[*]` section applies to every seat:
+//! ```text
+//! [*]
+//! layout = us
+//! repeat_rate = 25
+//! repeat_delay = 600
+//!
+//! [seat0]
+//! layout = it
+//! options = caps:escape
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// XKB names used to compile the keymap of a keyboard.
+/// Empty fields fall back to the xkbcommon defaults, that honour the `XKB_DEFAULT_*` variables.
+pub struct Keymap {
+    pub rules: String,
+    pub model: String,
+    pub layout: String,
+    pub variant: String,
+    pub options: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Keyboard settings of a seat, unset fields are left to the platform or to the defaults.
+pub struct KeyboardSettings {
+    pub rules: Option<String>,
+    pub model: Option<String>,
+    pub layout: Option<String>,
+    pub variant: Option<String>,
+    pub options: Option<String>,
+    pub rate: Option<i32>,
+    pub delay: Option<i32>,
+}
+impl KeyboardSettings {
+    /// Build the keymap described by these settings.
+    pub fn keymap(&self) -> Keymap {
+        Keymap {
+            rules: self.rules.clone().unwrap_or_default(),
+            model: self.model.clone().unwrap_or_default(),
+            layout: self.layout.clone().unwrap_or_default(),
+            variant: self.variant.clone().unwrap_or_default(),
+            options: self.options.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Check if the keymap is configured, so that the platform layout must not override it.
+    pub fn has_keymap(&self) -> bool {
+        self.rules.is_some()
+            || self.model.is_some()
+            || self.layout.is_some()
+            || self.variant.is_some()
+            || self.options.is_some()
+    }
+
+    /// Check if the repeat rate and delay are configured.
+    pub fn has_repeat(&self) -> bool {
+        self.rate.is_some() || self.delay.is_some()
+    }
+
+    /// Fill the unset fields with the ones of `other`.
+    fn or(self, other: &Self) -> Self {
+        Self {
+            rules: self.rules.or_else(|| other.rules.clone()),
+            model: self.model.or_else(|| other.model.clone()),
+            layout: self.layout.or_else(|| other.layout.clone()),
+            variant: self.variant.or_else(|| other.variant.clone()),
+            options: self.options.or_else(|| other.options.clone()),
+            rate: self.rate.or(other.rate),
+            delay: self.delay.or(other.delay),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        let value = value.to_string();
+        match key {
+            "rules" => self.rules = Some(value),
+            "model" => self.model = Some(value),
+            "layout" => self.layout = Some(value),
+            "variant" => self.variant = Some(value),
+            "options" => self.options = Some(value),
+            "repeat_rate" => self.rate = Some(value.parse().ok()?),
+            "repeat_delay" => self.delay = Some(value.parse().ok()?),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+#[derive(Debug, Default)]
+/// Keyboard settings of all the seats, read from a config file.
+pub struct KeyboardConfig {
+    seats: BTreeMap<String, KeyboardSettings>,
+}
+impl KeyboardConfig {
+    /// Name of the section applied to every seat.
+    const ANY_SEAT: &'static str = "*";
+
+    /// Load the config from `path`, a missing file is considered empty.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let seats = path
+            .as_ref()
+            .map(|path| match std::fs::read_to_string(path) {
+                Ok(content) => Self::parse(&content),
+                Err(error) => {
+                    if error.kind() != std::io::ErrorKind::NotFound {
+                        log::error!(target: "Keyboard Config","Failed to read {:?}: {}",path,error);
+                    }
+                    BTreeMap::new()
+                }
+            })
+            .unwrap_or(BTreeMap::new());
+        Self { seats }
+    }
+
+    /// Get the settings of a seat, falling back to the ones of every seat.
+    pub fn settings(&self, seat: &str) -> KeyboardSettings {
+        let any = self.seats.get(Self::ANY_SEAT).cloned().unwrap_or_default();
+        self.seats
+            .get(seat)
+            .cloned()
+            .map(|settings| settings.or(&any))
+            .unwrap_or(any)
+    }
+
+    fn parse(content: &str) -> BTreeMap<String, KeyboardSettings> {
+        let mut seats = BTreeMap::new();
+        let mut current: Option<String> = None;
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(seat) = line
+                .strip_prefix('[')
+                .map(|line| line.strip_suffix(']'))
+                .flatten()
+            {
+                let seat = seat.trim().to_string();
+                seats
+                    .entry(seat.clone())
+                    .or_insert(KeyboardSettings::default());
+                current = Some(seat);
+                continue;
+            }
+            let parsed = match (current.as_ref(), line.split_once('=')) {
+                (Some(seat), Some((key, value))) => seats
+                    .get_mut(seat)
+                    .map(|settings: &mut KeyboardSettings| settings.set(key.trim(), value.trim()))
+                    .flatten(),
+                _ => None,
+            };
+            if parsed.is_none() {
+                log::error!(target: "Keyboard Config","Invalid line {} ignored: {}",index + 1,line)
+            }
+        }
+        seats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "
+[*]
+layout = us
+repeat_rate = 25
+repeat_delay = 600
+
+# Italian keyboard on the first seat
+[seat0]
+layout = it
+options = caps:escape
+";
+
+    fn config(content: &str) -> KeyboardConfig {
+        KeyboardConfig {
+            seats: KeyboardConfig::parse(content),
+        }
+    }
+
+    #[test]
+    fn seat_settings_fall_back_to_every_seat() {
+        let settings = config(CONTENT).settings("seat0");
+        assert_eq!(settings.layout.as_deref(), Some("it"));
+        assert_eq!(settings.options.as_deref(), Some("caps:escape"));
+        assert_eq!(settings.rate, Some(25));
+        assert_eq!(settings.delay, Some(600));
+    }
+
+    #[test]
+    fn unknown_seats_use_every_seat_settings() {
+        let settings = config(CONTENT).settings("seat1");
+        assert_eq!(settings.layout.as_deref(), Some("us"));
+        assert_eq!(settings.options, None);
+        assert!(settings.has_keymap());
+        assert!(settings.has_repeat());
+    }
+
+    #[test]
+    fn empty_config_leaves_everything_unset() {
+        let settings = config("").settings("seat0");
+        assert_eq!(settings, KeyboardSettings::default());
+        assert!(!settings.has_keymap());
+        assert!(!settings.has_repeat());
+        assert_eq!(settings.keymap(), Keymap::default());
+    }
+
+    #[test]
+    fn parse_ignores_invalid_lines() {
+        let content = "
+layout = us
+[seat0]
+repeat_rate = fast
+compose = ralt
+layout
+variant = intl
+";
+        assert_eq!(
+            config(content).settings("seat0"),
+            KeyboardSettings {
+                variant: Some(String::from("intl")),
+                ..KeyboardSettings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn keymap_uses_the_configured_names() {
+        let keymap = config(CONTENT).settings("seat0").keymap();
+        assert_eq!(keymap.layout, "it");
+        assert_eq!(keymap.options, "caps:escape");
+        assert_eq!(keymap.rules, "");
+    }
+}
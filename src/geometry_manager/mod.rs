@@ -2,15 +2,25 @@
 
 mod surface_manager;
 pub use surface_manager::{
-    PopupState, Surface, SurfaceEvent, SurfaceKind, SurfaceManager, SurfaceRequest,
+    PopupState, Surface, SurfaceEvent, SurfaceKind, SurfaceManager, SurfaceRequest, TileZone,
 };
 
 mod output_manager;
-pub use output_manager::{OutputEvent, OutputManager, OutputRequest};
+pub use output_manager::{Output, OutputEvent, OutputManager, OutputRequest, OutputTransform};
+
+mod output_profiles;
+pub use output_profiles::{OutputProfile, OutputProfiles, OutputSettings};
+
+mod keyboard_config;
+pub use keyboard_config::{KeyboardConfig, KeyboardSettings, Keymap};
+
+mod config;
+pub use config::{Config, Settings};
 
 mod seat_manager;
 pub use seat_manager::{
-    Cursor, CursorEvent, CursorRequest, KeyboardEvent, KeyboardRequest, SeatEvent, SeatManager,
+    ConstraintKind, Cursor, CursorEvent, CursorIcon, CursorImage, CursorRequest, Keyboard,
+    KeyboardEvent, KeyboardRequest, PointerConstraint, PopupGrab, SeatEvent, SeatManager,
     SeatRequest,
 };
 
@@ -71,6 +81,42 @@ pub enum WCompRequest {
     Output { request: OutputRequest },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Strategies to choose where new surfaces are placed.
+/// Surfaces are placed on the output the user is working on,
+/// that is the one holding the active surface or, if none, the cursor.
+pub enum PlacementStrategy {
+    /// Surfaces are centered on the output.
+    Centered,
+    /// Each surface is shifted from the active one, restarting from the output corner when it does not fit.
+    Cascade,
+    /// Surfaces are centered under the cursor.
+    UnderCursor,
+    /// Surfaces are placed where they overlap the existing ones the least.
+    Smart,
+}
+impl Default for PlacementStrategy {
+    fn default() -> Self {
+        Self::Centered
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Policies deciding which surface receives the keyboard focus.
+pub enum FocusPolicy {
+    /// The focus changes clicking on a surface.
+    Click,
+    /// The focus follows the cursor, it is lost when the cursor is over no surface.
+    FollowsMouse,
+    /// The focus follows the cursor, but it is kept when the cursor is over no surface.
+    Sloppy,
+}
+impl Default for FocusPolicy {
+    fn default() -> Self {
+        Self::Click
+    }
+}
+
 #[derive(Debug)]
 /// Manager that merge together the behaviour of
 /// the [seat manager][SeatManager], the [surface manager][SurfaceManager] and the [output manager][OutputManager].
@@ -78,20 +124,55 @@ pub struct GeometryManager {
     seat_manager: SeatManager,
     surface_manager: SurfaceManager,
     output_manager: OutputManager,
+    placement_strategy: PlacementStrategy,
+    focus_policy: FocusPolicy,
+    click_raise: bool,
     events: Vec<WCompRequest>,
 }
 
 impl GeometryManager {
-    pub fn new() -> Self {
-        let seat_manager = SeatManager::new();
+    /// Distance between surfaces placed with the [cascade][PlacementStrategy::Cascade] strategy.
+    const CASCADE_STEP: i32 = 32;
+    /// Distance between the positions tried by the [smart][PlacementStrategy::Smart] strategy.
+    const SMART_STEP: usize = 32;
+
+    pub fn new(config: Config) -> Self {
+        let seat_manager = SeatManager::new(config.keyboards);
         let surface_manager = SurfaceManager::new();
-        let output_manager = OutputManager::new();
+        let output_manager = OutputManager::new(config.output_profiles);
+        let placement_strategy = PlacementStrategy::default();
+        let focus_policy = FocusPolicy::default();
+        let click_raise = true;
         let events = Vec::new();
-        Self {
+        let mut geometry_manager = Self {
             seat_manager,
             surface_manager,
             output_manager,
+            placement_strategy,
+            focus_policy,
+            click_raise,
             events,
+        };
+        geometry_manager.apply_settings(&config.settings);
+        geometry_manager
+    }
+
+    /// Apply the general settings, leaving the unset ones unchanged.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        if let Some(focus_policy) = settings.focus_policy {
+            self.set_focus_policy(focus_policy);
+        }
+        if let Some(click_raise) = settings.click_raise {
+            self.set_click_raise(click_raise);
+        }
+        if let Some(placement_strategy) = settings.placement_strategy {
+            self.set_placement_strategy(placement_strategy);
+        }
+        if let Some(snap_threshold) = settings.snap_threshold {
+            self.set_snap_threshold(snap_threshold);
+        }
+        if let Some(tiling_threshold) = settings.tiling_threshold {
+            self.set_tiling_threshold(tiling_threshold);
         }
     }
 
@@ -130,16 +211,47 @@ impl GeometryManager {
     }
 
     /// Add the keyboard to the specified seat in the manager.
-    pub fn add_keyboard(
+    pub fn add_keyboard(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Keyboard added");
+        let events = self
+            .seat_manager
+            .add_keyboard(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Get the keyboard reference of a specific seat in the manager.
+    pub fn keyboard_ref(&self, id: usize) -> Option<&Keyboard> {
+        self.seat_manager.keyboard_ref(id)
+    }
+
+    /// Change the repeat rate and delay of the keyboard of the specified seat in the manager.
+    pub fn keyboard_repeat_info(
         &mut self,
         id: usize,
         rate: i32,
         delay: i32,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Keyboard added");
+        log::info!(target:"WComp","Geometry manager | Keyboard repeat info changed");
+        let events = self
+            .seat_manager
+            .keyboard_repeat_info(id, rate, delay)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Change the layout of the keyboard of the specified seat in the manager.
+    pub fn keyboard_layout(
+        &mut self,
+        id: usize,
+        layout: String,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Keyboard layout changed");
         let events = self
             .seat_manager
-            .add_keyboard(id, rate, delay)
+            .keyboard_layout(id, layout)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
@@ -174,6 +286,146 @@ impl GeometryManager {
         self.postprocess_events(events)
     }
 
+    /// Get the popup grab of a specific seat in the manager.
+    pub fn popup_grab_ref(&self, id: usize) -> Option<&PopupGrab> {
+        self.seat_manager.popup_grab_ref(id)
+    }
+
+    /// Get the grabbing popups of a specific seat that are kept
+    /// if a new grab is started on a child of `parent`.
+    pub fn popup_grab_chain(&self, id: usize, parent: usize) -> Vec<usize> {
+        self.seat_manager.popup_grab_chain(id, parent)
+    }
+
+    /// Start a popup grab on the specified seat in the manager.
+    pub fn popup_grab_start(
+        &mut self,
+        id: usize,
+        serial: u32,
+        surface: usize,
+        parent: Option<usize>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Popup grab started");
+        let events = self
+            .surface_manager
+            .toplevel_of(surface)
+            .map(|root| {
+                self.seat_manager
+                    .popup_grab_start(id, serial, surface, parent, root)
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Stop the popup grab of the specified seat in the manager.
+    pub fn popup_grab_stop(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Popup grab stopped");
+        let events = self
+            .seat_manager
+            .popup_grab_stop(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Move the keyboard focus of a seat to the next or previous visible top-level surface, raising it.
+    /// Surfaces are cycled in creation order, so that raising them does not alter the cycle.
+    pub fn cycle_keyboard_focus(
+        &mut self,
+        id: usize,
+        forward: bool,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Keyboard focus cycled");
+        let mut surfaces = self
+            .surface_manager
+            .toplevel_surfaces_ref()
+            .filter(|surface| !surface.is_minimized() && surface.buffer().is_some())
+            .map(|surface| surface.id())
+            .collect::<Vec<_>>();
+        surfaces.sort();
+        let current = self
+            .surface_manager
+            .active_surface()
+            .map(|active| surfaces.iter().position(|surface| *surface == active))
+            .flatten();
+        let next = match (current, surfaces.len()) {
+            (_, 0) => None,
+            (Some(current), len) if forward => Some(surfaces[(current + 1) % len]),
+            (Some(current), len) => Some(surfaces[(current + len - 1) % len]),
+            (None, _) => surfaces.first().cloned(),
+        };
+        let events = next
+            .map(|next| {
+                self.seat_manager
+                    .keyboard_focus(id, Some(next))
+                    .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                    .map(WCompEvent::from)
+                    .chain(
+                        self.surface_manager
+                            .raise_surface(next)
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from),
+                    )
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or(Vec::new());
+        self.postprocess_events(events.into_iter())
+    }
+
+    /// Move the cursor of a seat to the center of the next output,
+    /// focusing the topmost surface shown there.
+    pub fn switch_output(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Output switched");
+        let outputs = self
+            .output_manager
+            .outputs_ref()
+            .map(|output| (output.id, output.geometry.clone()))
+            .collect::<Vec<_>>();
+        let current = self
+            .seat_manager
+            .cursor_ref(id)
+            .map(|cursor| cursor.output().clone())
+            .flatten()
+            .map(|current| outputs.iter().position(|(output, _)| *output == current))
+            .flatten();
+        let next = match (current, outputs.len()) {
+            (_, 0) => None,
+            (Some(current), len) => Some(outputs[(current + 1) % len].clone()),
+            (None, _) => outputs.first().cloned(),
+        };
+        let (output, geometry) = match next {
+            Some(next) => next,
+            None => return self.postprocess_events(Vec::new().into_iter()),
+        };
+        let center = pal::Position2D::from((
+            geometry.position.x + geometry.size.width as i32 / 2,
+            geometry.position.y + geometry.size.height as i32 / 2,
+        ));
+        let topmost = self
+            .surface_manager
+            .toplevel_surfaces_ref()
+            .find(|surface| !surface.is_minimized() && surface.output() == Some(output))
+            .map(|surface| surface.id());
+
+        let mut events = self
+            .seat_manager
+            .move_cursor(id, center)
+            .collect::<Vec<_>>();
+        if let Some(topmost) = topmost {
+            events.extend(self.seat_manager.keyboard_focus(id, Some(topmost)));
+        }
+        let events = events
+            .into_iter()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        self.postprocess_events(events.into_iter())
+    }
+
     /// Get the cursor reference of a specific seat in the manager.
     pub fn cursor_ref(&self, id: usize) -> Option<&Cursor> {
         self.seat_manager.cursor_ref(id)
@@ -184,7 +436,7 @@ impl GeometryManager {
         &mut self,
         id: usize,
         position: pal::Position2D<i32>,
-        image: Option<usize>,
+        image: CursorImage,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         log::info!(target:"WComp","Geometry manager | Cursor added");
         let events = self
@@ -255,6 +507,7 @@ impl GeometryManager {
     */
 
     /// Transform output relative coordinates to absolut compositor coordinates.
+    /// Relative coordinates are physical, absolute ones are logical.
     pub fn relative_to_absolute(
         &self,
         output_id: usize,
@@ -283,15 +536,86 @@ impl GeometryManager {
     }
 
     /// Send a cursor move event to the cursor of the specified seat in the manager.
+    /// The position is limited by the active pointer constraint and kept inside the output layout.
     pub fn move_cursor(
         &mut self,
         id: usize,
         position: pal::Position2D<i32>,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         log::info!(target:"WComp","Geometry manager | Cursor moved");
+        let events = self
+            .constrain_position(id, position)
+            .map(|position| {
+                self.seat_manager
+                    .move_cursor(id, position)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or(Vec::new())
+            .into_iter()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Constrain the cursor of the specified seat while a surface has its focus.
+    pub fn constrain_cursor(
+        &mut self,
+        id: usize,
+        surface: usize,
+        kind: ConstraintKind,
+        region: Option<Vec<pal::Rectangle<i32, u32>>>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Cursor constrained");
+        let mut events = self
+            .seat_manager
+            .constrain_cursor(id, surface, kind, region)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        events.append(&mut self.update_pointer_constraint(id));
+        self.postprocess_events(events.into_iter())
+    }
+
+    /// Remove the constraint a surface set on the cursor of the specified seat.
+    pub fn unconstrain_cursor(
+        &mut self,
+        id: usize,
+        surface: usize,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Cursor unconstrained");
+        let events = self
+            .seat_manager
+            .unconstrain_cursor(id, surface)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Change the image of the cursor of the specified seat in the manager.
+    pub fn set_cursor_image(
+        &mut self,
+        id: usize,
+        image: CursorImage,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Cursor image changed");
+        let events = self
+            .seat_manager
+            .set_cursor_image(id, image)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Notify the new content of a client surface used as cursor image.
+    pub fn commit_cursor_surface(
+        &mut self,
+        handle: ews::WlSurface,
+        hotspot: pal::Offset2D<i32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Cursor surface committed");
         let events = self
             .seat_manager
-            .move_cursor(id, position)
+            .commit_cursor_surface(handle, hotspot)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
@@ -307,6 +631,7 @@ impl GeometryManager {
         state: pal::State,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         log::info!(target:"WComp","Geometry manager | Cursor button");
+        let pressed = matches!(state, pal::State::Down);
         let focus = self
             .seat_manager
             .cursor_ref(id)
@@ -315,19 +640,37 @@ impl GeometryManager {
             .flatten()
             .map(|surface| surface.id());
 
-        let events = self
+        let mut events = self
             .seat_manager
             .cursor_button(id, time, code, key, state)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from)
-            .chain(
+            .collect::<Vec<_>>();
+        //Clicking focuses the surface with every policy, raising it is a separate option.
+        if pressed {
+            events.extend(
                 self.seat_manager
                     .keyboard_focus(id, focus)
                     .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
                     .map(WCompEvent::from),
             );
+        }
+        if let (true, true, Some(toplevel)) = (
+            pressed,
+            self.click_raise,
+            focus
+                .map(|focus| self.surface_manager.toplevel_of(focus))
+                .flatten(),
+        ) {
+            events.extend(
+                self.surface_manager
+                    .raise_surface(toplevel)
+                    .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                    .map(WCompEvent::from),
+            );
+        }
 
-        self.postprocess_events(events)
+        self.postprocess_events(events.into_iter())
     }
 
     /// Send a cursor axis event to the cursor of the specified seat in the manager.
@@ -353,13 +696,14 @@ impl GeometryManager {
     pub fn add_output(
         &mut self,
         id: usize,
+        name: String,
         handle: std::sync::Arc<pal::wgpu::Surface>,
         size: pal::Size2D<u32>,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         log::info!(target:"WComp","Geometry manager | Output added");
         let events = self
             .output_manager
-            .add_output(id, handle, size)
+            .add_output(id, name, handle, size)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
@@ -376,166 +720,769 @@ impl GeometryManager {
         self.postprocess_events(events)
     }
 
-    /// Resize an output in the manager.
-    pub fn resize_output(
-        &mut self,
-        id: usize,
-        size: pal::Size2D<u32>,
-    ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Output resized");
+    /// Enable an output in the manager.
+    pub fn enable_output(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Output enabled");
         let events = self
             .output_manager
-            .resize_output(id, size)
+            .enable_output(id)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
     }
 
-    /// Get the optimal size for a new surface.
-    pub fn get_surface_optimal_size(&self) -> pal::Size2D<u32> {
-        self.output_manager.get_surface_optimal_size()
-    }
-
-    /// Get the optimal position for a new surface.
-    pub fn get_surface_optimal_position(
-        &self,
-        size: &pal::Size2D<u32>,
-    ) -> (pal::Position2D<i32>, u32) {
-        (self.output_manager.get_surface_optimal_position(size), 0)
-    }
-
-    /// Get the references of all the surfaces.
-    pub fn surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
-        self.surface_manager.surfaces_ref()
-    }
-
-    /// Get the reference of a specific surface.
-    pub fn surface_ref(&self, id: usize) -> Option<&Surface> {
-        self.surface_manager.surface_ref(id)
-    }
-
-    /// Add a surface to the manager.
-    pub fn add_surface(
-        &mut self,
-        id: usize,
-        kind: SurfaceKind,
-        position: pal::Position2D<i32>,
-    ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface added");
+    /// Disable an output in the manager.
+    pub fn disable_output(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Output disabled");
         let events = self
-            .surface_manager
-            .add_surface(id, kind, position)
+            .output_manager
+            .disable_output(id)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
-
         self.postprocess_events(events)
     }
 
-    /// Remove a surface from the manager.
-    pub fn del_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface removed");
+    /// Resize an output in the manager.
+    pub fn resize_output(
+        &mut self,
+        id: usize,
+        size: pal::Size2D<u32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Output resized");
         let events = self
-            .surface_manager
-            .del_surface(id)
+            .output_manager
+            .resize_output(id, size)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
     }
 
-    /// Start the interactive resize of a surface in the manager.
-    pub fn interactive_resize_start(
+    /// Move an output in the manager.
+    pub fn move_output(
         &mut self,
         id: usize,
-        serial: u32,
-        edge: ews::ResizeEdge,
+        position: pal::Position2D<i32>,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Interactive resize started on surface {}", id);
+        log::info!(target:"WComp","Geometry manager | Output moved");
         let events = self
-            .surface_manager
-            .interactive_resize_start(id, serial, edge)
+            .output_manager
+            .move_output(id, position)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
     }
 
-    /// End the interactive resize (previously started) of a surface in the manager.
-    pub fn interactive_resize_end(
+    /// Change the scale of an output in the manager.
+    pub fn scale_output(
         &mut self,
         id: usize,
-        serial: u32,
+        scale: f64,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Interactive resize stopped on surface {}", id);
+        log::info!(target:"WComp","Geometry manager | Output scaled");
         let events = self
-            .surface_manager
-            .interactive_resize_end(id, serial)
+            .output_manager
+            .scale_output(id, scale)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
     }
 
-    /// Resize of a surface in the manager.
-    pub fn resize_surface(
+    /// Change the transform of an output in the manager.
+    pub fn transform_output(
         &mut self,
         id: usize,
-        size: pal::Size2D<u32>,
+        transform: OutputTransform,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface {} resized to {}", id, size);
+        log::info!(target:"WComp","Geometry manager | Output transformed");
         let events = self
-            .surface_manager
-            .resize_surface(id, size)
+            .output_manager
+            .transform_output(id, transform)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
             .map(WCompEvent::from);
         self.postprocess_events(events)
     }
 
-    /// Maximize of a surface in the manager.
-    pub fn maximize_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface {} maximized", id);
-        let events = self
-            .surface_manager
-            .maximize_surface(id)
-            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
-            .map(WCompEvent::from);
-        self.postprocess_events(events)
+    /// Get the reference of an output.
+    pub fn output_ref(&self, id: usize) -> Option<&Output> {
+        self.output_manager
+            .output_ref(id)
+            .map(|(_index, output)| output)
     }
 
-    /// Unmaximize of a surface in the manager.
-    pub fn unmaximize_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface {} unmaximized", id);
-        let events = self
-            .surface_manager
-            .unmaximize_surface(id)
-            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
-            .map(WCompEvent::from);
-        self.postprocess_events(events)
+    /// Get the references of all the outputs.
+    pub fn outputs_ref(&self) -> impl Iterator<Item = &Output> {
+        self.output_manager.outputs_ref()
     }
 
-    /// Perform a interactive resize step of a surface in the manager.
-    pub fn interactive_resize_surface(
-        &mut self,
-        id: usize,
-        serial: u32,
-        inner_size: pal::Size2D<u32>,
-    ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Surface {} resized interactively to {}", id, inner_size);
-        let events = self
-            .surface_manager
-            .interactive_resize_surface(id, serial, inner_size)
-            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
-            .map(WCompEvent::from);
-        self.postprocess_events(events)
+    /// Get the position of an output in the physical space of the renderer.
+    pub fn output_physical_position(&self, output: &Output) -> pal::Position2D<i32> {
+        self.output_manager.physical_position(output)
     }
 
-    /// Change the configuration of a surface in the manager.
-    pub fn configure_surface(
-        &mut self,
-        id: usize,
-        inner_geometry: Option<pal::Rectangle<i32, u32>>,
-        min_size: pal::Size2D<u32>,
-        max_size: pal::Size2D<u32>,
-    ) -> impl Iterator<Item = WCompEvent> + Clone {
-        log::info!(target:"WComp","Geometry manager | Configuring surface {}", id);
-        let events = self
+    /// Transform a logical rectangle into the physical space of the renderer.
+    pub fn to_physical(&self, geometry: &pal::Rectangle<i32, u32>) -> pal::Rectangle<i32, u32> {
+        self.output_manager.to_physical(geometry)
+    }
+
+    /// Set the policy deciding which surface receives the keyboard focus.
+    pub fn set_focus_policy(&mut self, focus_policy: FocusPolicy) {
+        self.focus_policy = focus_policy;
+    }
+
+    /// Set whether clicking on a surface raises it, independently from the focus policy.
+    pub fn set_click_raise(&mut self, click_raise: bool) {
+        self.click_raise = click_raise;
+    }
+
+    /// Set the strategy used to place new surfaces.
+    pub fn set_placement_strategy(&mut self, placement_strategy: PlacementStrategy) {
+        self.placement_strategy = placement_strategy;
+    }
+
+    /// Get the optimal size for a new surface.
+    pub fn get_surface_optimal_size(&self) -> pal::Size2D<u32> {
+        self.output_manager
+            .get_surface_optimal_size(self.working_output())
+    }
+
+    /// Get the optimal position for a new surface, according to the [placement strategy][PlacementStrategy].
+    pub fn get_surface_optimal_position(
+        &self,
+        size: &pal::Size2D<u32>,
+    ) -> (pal::Position2D<i32>, u32) {
+        let output = self.working_output();
+        let centered = self
+            .output_manager
+            .get_surface_optimal_position(output, size);
+        let bounds = match self
+            .output_manager
+            .get_output_at(&centered)
+            .or(self.output_manager.outputs_ref().next())
+        {
+            Some(output) => output.geometry.clone(),
+            None => return (centered, 0),
+        };
+        let position = match self.placement_strategy {
+            PlacementStrategy::Centered => centered,
+            PlacementStrategy::Cascade => self.cascade_position(&bounds, size),
+            PlacementStrategy::UnderCursor => self.under_cursor_position(size).unwrap_or(centered),
+            PlacementStrategy::Smart => self.smart_position(&bounds, size),
+        };
+        (position, 0)
+    }
+
+    /// Get the output the user is working on: the one holding the active surface,
+    /// otherwise the one holding a cursor.
+    fn working_output(&self) -> Option<usize> {
+        self.surface_manager
+            .active_surface()
+            .map(|id| self.surface_manager.surface_ref(id))
+            .flatten()
+            .map(|surface| surface.output())
+            .flatten()
+            .or_else(|| {
+                self.seat_manager
+                    .cursors_ref()
+                    .find_map(|cursor| cursor.output().clone())
+            })
+    }
+
+    fn cascade_position(
+        &self,
+        bounds: &pal::Rectangle<i32, u32>,
+        size: &pal::Size2D<u32>,
+    ) -> pal::Position2D<i32> {
+        let step = pal::Position2D::from((Self::CASCADE_STEP, Self::CASCADE_STEP));
+        let position = self
+            .surface_manager
+            .active_surface()
+            .map(|id| self.surface_manager.surface_ref(id))
+            .flatten()
+            .map(|surface| surface.position().clone())
+            .filter(|position| bounds.contains(position))
+            .unwrap_or(bounds.position.clone())
+            + step;
+        let fits = position.x + size.width as i32 <= bounds.x_offset()
+            && position.y + size.height as i32 <= bounds.position.y + bounds.size.height as i32;
+        if fits {
+            position
+        } else {
+            bounds.position.clone()
+        }
+    }
+
+    fn under_cursor_position(&self, size: &pal::Size2D<u32>) -> Option<pal::Position2D<i32>> {
+        let cursor = self
+            .seat_manager
+            .cursors_ref()
+            .find(|cursor| cursor.output().is_some())?
+            .position()
+            .clone();
+        let bounds = self.output_manager.get_output_at(&cursor)?.geometry.clone();
+        let position = pal::Position2D::from((
+            cursor.x - size.width as i32 / 2,
+            cursor.y - size.height as i32 / 2,
+        ));
+        Some(Self::clamp_into(position, size, &bounds))
+    }
+
+    fn smart_position(
+        &self,
+        bounds: &pal::Rectangle<i32, u32>,
+        size: &pal::Size2D<u32>,
+    ) -> pal::Position2D<i32> {
+        let surfaces = self
+            .surface_manager
+            .toplevel_surfaces_ref()
+            .filter(|surface| !surface.is_minimized())
+            .filter_map(|surface| surface.geometry())
+            .collect::<Vec<_>>();
+        let overlap = |position: &pal::Position2D<i32>| -> u64 {
+            surfaces
+                .iter()
+                .map(|geometry| {
+                    let width = (position.x + size.width as i32).min(geometry.x_offset())
+                        - position.x.max(geometry.position.x);
+                    let height = (position.y + size.height as i32)
+                        .min(geometry.position.y + geometry.size.height as i32)
+                        - position.y.max(geometry.position.y);
+                    width.max(0) as u64 * height.max(0) as u64
+                })
+                .sum()
+        };
+        let max_x = (bounds.size.width as i32 - size.width as i32).max(0);
+        let max_y = (bounds.size.height as i32 - size.height as i32).max(0);
+        //The first position with the least overlap is taken, scanning rows from the top-left corner.
+        (0..=max_y)
+            .step_by(Self::SMART_STEP)
+            .map(|y| {
+                (0..=max_x)
+                    .step_by(Self::SMART_STEP)
+                    .map(move |x| bounds.position.clone() + pal::Position2D::from((x, y)))
+            })
+            .flatten()
+            .fold(
+                None,
+                |best: Option<(pal::Position2D<i32>, u64)>, position| {
+                    let overlap = overlap(&position);
+                    match best {
+                        Some(best) if best.1 <= overlap => Some(best),
+                        _ => Some((position, overlap)),
+                    }
+                },
+            )
+            .map(|(position, _overlap)| position)
+            .unwrap_or(bounds.position.clone())
+    }
+
+    /// Move `position` to the closest point inside any of `rectangles`.
+    fn clamp_to_rectangles<'a>(
+        position: pal::Position2D<i32>,
+        rectangles: impl Iterator<Item = &'a pal::Rectangle<i32, u32>>,
+    ) -> Option<pal::Position2D<i32>> {
+        let point = pal::Size2D::from((1, 1));
+        rectangles
+            .map(|rectangle| Self::clamp_into(position.clone(), &point, rectangle))
+            .min_by_key(|clamped| {
+                let x = (clamped.x - position.x) as i64;
+                let y = (clamped.y - position.y) as i64;
+                x * x + y * y
+            })
+    }
+
+    /// Apply the active pointer constraint of a seat and the output layout to a cursor position.
+    /// Returns `None` if the cursor is locked in place.
+    fn constrain_position(
+        &self,
+        id: usize,
+        position: pal::Position2D<i32>,
+    ) -> Option<pal::Position2D<i32>> {
+        let constraint = self
+            .seat_manager
+            .cursor_ref(id)
+            .map(|cursor| cursor.active_constraint())
+            .flatten();
+        let position = match constraint {
+            Some(constraint) if constraint.kind() == ConstraintKind::Lock => return None,
+            Some(constraint) => {
+                let region = self.constraint_region(constraint);
+                Self::clamp_to_rectangles(position.clone(), region.iter()).unwrap_or(position)
+            }
+            None => position,
+        };
+        //Clamping to the closest output keeps the cursor on the edge it crossed,
+        //even where outputs of different sizes leave uncovered areas.
+        let outputs = self
+            .output_manager
+            .outputs_ref()
+            .map(|output| &output.geometry);
+        Some(Self::clamp_to_rectangles(position.clone(), outputs).unwrap_or(position))
+    }
+
+    /// Update the surface focused by every cursor after the surfaces under it changed
+    /// without the cursor moving, releasing the constraints that no longer apply.
+    fn refresh_cursors_focus(&mut self) -> Vec<WCompEvent> {
+        let ids = self.seat_manager.cursor_seats().collect::<Vec<_>>();
+        let mut events = Vec::new();
+        for id in ids {
+            let position = match self.seat_manager.cursor_ref(id) {
+                Some(cursor) => cursor.position().clone(),
+                None => continue,
+            };
+            let focus = self.get_surface_at(&position).map(|surface| surface.id());
+            events.extend(
+                self.seat_manager
+                    .focus_cursor(id, focus)
+                    .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                    .map(WCompEvent::from),
+            );
+            events.append(&mut self.update_pointer_constraint(id));
+        }
+        events
+    }
+
+    /// Get the absolute rectangles of the region of a pointer constraint.
+    fn constraint_region(&self, constraint: &PointerConstraint) -> Vec<pal::Rectangle<i32, u32>> {
+        self.surface_manager
+            .surface_ref(constraint.surface())
+            .map(|surface| match constraint.region() {
+                Some(region) => region
+                    .iter()
+                    .map(|rectangle| {
+                        let position = surface.position().clone() + rectangle.position.clone();
+                        pal::Rectangle::from((position, rectangle.size.clone()))
+                    })
+                    .collect(),
+                None => surface.geometry().into_iter().collect(),
+            })
+            .unwrap_or(Vec::new())
+    }
+
+    /// Activate the constraint of the surface focused by the cursor once the cursor is inside its region,
+    /// deactivating the active one if the focus moved to another surface.
+    /// Constraints only apply while the surface belongs to the active top-level surface.
+    fn update_pointer_constraint(&mut self, id: usize) -> Vec<WCompEvent> {
+        let cursor = match self.seat_manager.cursor_ref(id) {
+            Some(cursor) => cursor,
+            None => return Vec::new(),
+        };
+        let active_surface = self.surface_manager.active_surface();
+        let focus = cursor.focus().clone().filter(|focus| {
+            active_surface.is_some() && self.surface_manager.toplevel_of(*focus) == active_surface
+        });
+        let position = cursor.position().clone();
+        let active = cursor
+            .active_constraint()
+            .map(|constraint| constraint.surface());
+        let candidate = focus
+            .map(|focus| cursor.constraint(focus))
+            .flatten()
+            .filter(|constraint| !constraint.is_active())
+            .filter(|constraint| {
+                self.constraint_region(constraint)
+                    .iter()
+                    .any(|rectangle| rectangle.contains(&position))
+            })
+            .map(|constraint| constraint.surface());
+
+        let events = match (active, candidate) {
+            (_, Some(candidate)) => self
+                .seat_manager
+                .activate_constraint(id, candidate)
+                .collect::<Vec<_>>(),
+            (Some(active), None) if Some(active) != focus => self
+                .seat_manager
+                .deactivate_constraint(id)
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+        events
+            .into_iter()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect()
+    }
+
+    /// Move `position` so that a surface of `size` lies inside `bounds`, as long as it fits.
+    fn clamp_into(
+        position: pal::Position2D<i32>,
+        size: &pal::Size2D<u32>,
+        bounds: &pal::Rectangle<i32, u32>,
+    ) -> pal::Position2D<i32> {
+        let max_x = bounds.position.x + (bounds.size.width as i32 - size.width as i32).max(0);
+        let max_y = bounds.position.y + (bounds.size.height as i32 - size.height as i32).max(0);
+        pal::Position2D::from((
+            position.x.max(bounds.position.x).min(max_x),
+            position.y.max(bounds.position.y).min(max_y),
+        ))
+    }
+
+    /// Get the references of all the surfaces.
+    pub fn surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
+        self.surface_manager.surfaces_ref()
+    }
+
+    /// Get the reference of a specific surface.
+    pub fn surface_ref(&self, id: usize) -> Option<&Surface> {
+        self.surface_manager.surface_ref(id)
+    }
+
+    /// Get the id of the active top-level surface.
+    pub fn active_surface(&self) -> Option<usize> {
+        self.surface_manager.active_surface()
+    }
+
+    /// Add a surface to the manager.
+    pub fn add_surface(
+        &mut self,
+        id: usize,
+        kind: SurfaceKind,
+        position: pal::Position2D<i32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface added");
+        let events = self
+            .surface_manager
+            .add_surface(id, kind, position)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+
+        self.postprocess_events(events)
+    }
+
+    /// Remove a surface from the manager.
+    pub fn del_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface removed");
+        let events = self
+            .surface_manager
+            .del_surface(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Set the distance in pixels under which a moving surface snaps to output and surface edges.
+    pub fn set_snap_threshold(&mut self, snap_threshold: u32) {
+        self.surface_manager.set_snap_threshold(snap_threshold);
+    }
+
+    /// Set the distance in pixels from the output edges under which a dragged surface gets tiled.
+    pub fn set_tiling_threshold(&mut self, tiling_threshold: u32) {
+        self.surface_manager.set_tiling_threshold(tiling_threshold);
+    }
+
+    /// Get the depth the tiling preview is drawn at.
+    pub fn preview_depth(&self) -> u32 {
+        self.surface_manager.preview_depth()
+    }
+
+    /// Set the minimum size any surface can be interactively resized to.
+    pub fn set_min_surface_size(&mut self, min_surface_size: pal::Size2D<u32>) {
+        self.surface_manager.set_min_surface_size(min_surface_size);
+    }
+
+    /// Start the interactive move of a surface in the manager.
+    pub fn interactive_move_start(
+        &mut self,
+        id: usize,
+        serial: u32,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Interactive move started on surface {}", id);
+        let events = self
+            .surface_manager
+            .interactive_move_start(id, serial)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Interactively move a surface in the manager.
+    /// Dragging the cursor against the edges of an output previews the tiling of the surface,
+    /// applied when the move ends.
+    pub fn interactive_move_surface(
+        &mut self,
+        id: usize,
+        serial: u32,
+        position: pal::Position2D<i32>,
+        cursor: pal::Position2D<i32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Interactive move of surface {}", id);
+        let moving = self
+            .surface_manager
+            .surface_ref(id)
+            .map(|surface| surface.state())
+            .flatten()
+            .map(|state| state.is_moving_width(serial))
+            == Some(true);
+        if !moving {
+            return Vec::new().into_iter();
+        }
+
+        //Dragging a tiled surface away restores its original size under the cursor,
+        //whose offset from the surface replaces the one of the move grab from then on.
+        let untile_events = self
+            .surface_manager
+            .untile_surface(id, &cursor)
+            .collect::<Vec<_>>();
+        let move_offset = self
+            .surface_manager
+            .surface_ref(id)
+            .map(|surface| surface.state())
+            .flatten()
+            .map(|state| state.move_offset().cloned())
+            .flatten();
+        let position = match move_offset {
+            Some(move_offset) => cursor.clone() - move_offset,
+            None => position,
+        };
+
+        let bounds = self
+            .output_manager
+            .outputs_ref()
+            .map(|output| output.geometry.clone())
+            .collect::<Vec<_>>();
+        let position = self.surface_manager.snap_position(id, position, &bounds);
+        let threshold = self.surface_manager.tiling_threshold() as i32;
+        let preview = self
+            .output_manager
+            .get_output_at(&cursor)
+            .map(|output| {
+                TileZone::from_position(&cursor, &output.geometry, threshold)
+                    .map(|zone| (zone, zone.geometry(&output.geometry)))
+            })
+            .flatten();
+
+        let events = untile_events
+            .into_iter()
+            .chain(
+                self.surface_manager
+                    .interactive_move_surface(id, serial, position)
+                    .collect::<Vec<_>>(),
+            )
+            .chain(
+                self.surface_manager
+                    .set_tiling_preview(id, preview)
+                    .collect::<Vec<_>>(),
+            )
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        self.postprocess_events(events.into_iter())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// End the interactive move (previously started) of a surface in the manager.
+    /// If the cursor was resting against an output edge, the surface gets tiled.
+    pub fn interactive_move_end(
+        &mut self,
+        id: usize,
+        serial: u32,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Interactive move stopped on surface {}", id);
+        let preview = self.surface_manager.tiling_preview(id);
+        let mut events = self
+            .surface_manager
+            .interactive_move_end(id, serial)
+            .collect::<Vec<_>>();
+        if !events.is_empty() {
+            events.append(&mut self.surface_manager.set_tiling_preview(id, None).collect());
+            //Dropping on the top edge tiles to the whole output, so that dragging away restores the surface.
+            match preview {
+                Some((zone, geometry)) => events.append(
+                    &mut self
+                        .surface_manager
+                        .tile_surface(id, zone, geometry)
+                        .collect(),
+                ),
+                None => (),
+            }
+        }
+        let events = events
+            .into_iter()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        self.postprocess_events(events.into_iter())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Start the interactive resize of a surface in the manager.
+    pub fn interactive_resize_start(
+        &mut self,
+        id: usize,
+        serial: u32,
+        edge: ews::ResizeEdge,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Interactive resize started on surface {}", id);
+        let events = self
+            .surface_manager
+            .interactive_resize_start(id, serial, edge)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// End the interactive resize (previously started) of a surface in the manager.
+    pub fn interactive_resize_end(
+        &mut self,
+        id: usize,
+        serial: u32,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Interactive resize stopped on surface {}", id);
+        let events = self
+            .surface_manager
+            .interactive_resize_end(id, serial)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Resize of a surface in the manager.
+    pub fn resize_surface(
+        &mut self,
+        id: usize,
+        size: pal::Size2D<u32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} resized to {}", id, size);
+        let events = self
+            .surface_manager
+            .resize_surface(id, size)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Maximize of a surface in the manager.
+    pub fn maximize_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} maximized", id);
+        let events = self
+            .surface_manager
+            .maximize_surface(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Unmaximize of a surface in the manager.
+    pub fn unmaximize_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} unmaximized", id);
+        let events = self
+            .surface_manager
+            .unmaximize_surface(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Minimize a surface in the manager.
+    pub fn minimize_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} minimized", id);
+        let events = self
+            .surface_manager
+            .minimize_surface(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Restore a previously minimized surface in the manager.
+    pub fn restore_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} restored", id);
+        let events = self
+            .surface_manager
+            .restore_surface(id)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Get the references of all the minimized surfaces.
+    pub fn minimized_surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
+        self.surface_manager.minimized_surfaces_ref()
+    }
+
+    /// Check if a surface is currently shown, so not minimized nor child of a minimized one.
+    pub fn is_surface_visible(&self, id: usize) -> bool {
+        self.surface_manager.is_surface_visible(id)
+    }
+
+    /// Fullscreen a surface in the manager, optionally on a specific output.
+    pub fn fullscreen_surface(
+        &mut self,
+        id: usize,
+        output: Option<usize>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} fullscreened", id);
+        let events = self
+            .surface_manager
+            .fullscreen_surface(id, output)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Unfullscreen a surface in the manager.
+    pub fn unfullscreen_surface(&mut self, id: usize) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} unfullscreened", id);
+        let mut events = self
+            .surface_manager
+            .unfullscreen_surface(id)
+            .collect::<Vec<_>>();
+        //Tiled surfaces return to their zone on the output they are shown on.
+        let tile = self
+            .surface_manager
+            .surface_ref(id)
+            .map(|surface| {
+                let zone = surface.state().map(|state| state.tile_zone()).flatten()?;
+                let output = self.output_manager.output_ref(surface.output()?)?.1;
+                Some((zone, zone.geometry(&output.geometry)))
+            })
+            .flatten();
+        if let (false, Some((zone, geometry))) = (events.is_empty(), tile) {
+            events.append(
+                &mut self
+                    .surface_manager
+                    .tile_surface(id, zone, geometry)
+                    .collect(),
+            );
+        }
+        let events = events
+            .into_iter()
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        self.postprocess_events(events.into_iter())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Perform a interactive resize step of a surface in the manager.
+    pub fn interactive_resize_surface(
+        &mut self,
+        id: usize,
+        serial: u32,
+        inner_size: pal::Size2D<u32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Surface {} resized interactively to {}", id, inner_size);
+        let events = self
+            .surface_manager
+            .interactive_resize_surface(id, serial, inner_size)
+            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+            .map(WCompEvent::from);
+        self.postprocess_events(events)
+    }
+
+    /// Change the configuration of a surface in the manager.
+    pub fn configure_surface(
+        &mut self,
+        id: usize,
+        inner_geometry: Option<pal::Rectangle<i32, u32>>,
+        min_size: pal::Size2D<u32>,
+        max_size: pal::Size2D<u32>,
+    ) -> impl Iterator<Item = WCompEvent> + Clone {
+        log::info!(target:"WComp","Geometry manager | Configuring surface {}", id);
+        let events = self
             .surface_manager
             .configure_surface(id, inner_geometry, min_size, max_size)
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
@@ -565,14 +1512,46 @@ impl GeometryManager {
         handle: ews::WlBuffer,
         inner_geometry: pal::Rectangle<i32, u32>,
         suggested_size: pal::Size2D<u32>,
+        scale: u32,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         log::info!(target:"WComp","Geometry manager | Buffer attached");
-        let events = self
+        //Toplevels are placed once, when their first buffer tells their actual size,
+        //the ones fullscreened before it get fitted again now that their decorations are known.
+        let first_map = self
+            .surface_manager
+            .surface_ref(id)
+            .filter(|surface| surface.buffer().is_none())
+            .map(|surface| surface.state())
+            .flatten()
+            .map(|state| {
+                (
+                    state.is_fullscreen(),
+                    state.is_maximized() || state.is_tiled(),
+                )
+            });
+        let placement_size = inner_geometry.size.clone();
+        let mut events = self
             .surface_manager
-            .attach_buffer(id, handle, inner_geometry, suggested_size)
+            .attach_buffer(id, handle, inner_geometry, suggested_size, scale)
+            .collect::<Vec<_>>();
+        match first_map {
+            Some((true, _)) => {
+                events.extend(self.surface_manager.fullscreen_surface(id, None));
+            }
+            Some((false, false)) => {
+                let (position, _depth) = self.get_surface_optimal_position(&placement_size);
+                events.extend(self.surface_manager.move_surface(id, position));
+            }
+            _ => (),
+        }
+        let events = events
+            .into_iter()
             .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
-            .map(WCompEvent::from);
-        self.postprocess_events(events)
+            .map(WCompEvent::from)
+            .collect::<Vec<_>>();
+        self.postprocess_events(events.into_iter())
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 
     /// Detach a buffer from a surface in the manager.
@@ -602,12 +1581,43 @@ impl GeometryManager {
         events: impl Iterator<Item = WCompEvent> + Clone,
     ) -> impl Iterator<Item = WCompEvent> + Clone {
         let mut additional_events = Vec::new();
+
+        //Surfaces follow the outputs they are on, before being fitted into the new layout.
+        let moved_outputs = events
+            .clone()
+            .filter_map(|event| match event {
+                WCompEvent::Output {
+                    serial: _,
+                    event:
+                        OutputEvent::Moved {
+                            id,
+                            position: _,
+                            offset,
+                        },
+                } => Some((id, offset)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !moved_outputs.is_empty() {
+            additional_events.append(
+                &mut self
+                    .follow_moved_outputs(moved_outputs)
+                    .into_iter()
+                    .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                    .map(WCompEvent::from)
+                    .collect(),
+            );
+        }
         events.clone().for_each(|event| {
             match event {
                 WCompEvent::Seat {
                     serial: _,
                     event: SeatEvent::Keyboard(KeyboardEvent::Focus { id: _, surface }),
                 } => {
+                    //Focusing a popup activates the top-level surface owning it.
+                    let surface = surface
+                        .map(|surface| self.surface_manager.toplevel_of(surface))
+                        .flatten();
                     additional_events.append(
                         &mut self
                             .surface_manager
@@ -617,6 +1627,88 @@ impl GeometryManager {
                             .collect(),
                     );
                 }
+                WCompEvent::Seat {
+                    serial: _,
+                    event: SeatEvent::PopupGrabStarted { id, surface },
+                } => {
+                    //The topmost grabbing popup receives the keyboard input.
+                    additional_events.append(
+                        &mut self
+                            .seat_manager
+                            .keyboard_focus(id, Some(surface))
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from)
+                            .collect(),
+                    );
+                }
+                WCompEvent::Seat {
+                    serial: _,
+                    event:
+                        SeatEvent::PopupGrabStopped {
+                            id,
+                            serial: _,
+                            surfaces,
+                            focus,
+                        },
+                } => {
+                    additional_events.append(
+                        &mut self
+                            .restore_popup_grab_focus(id, &surfaces, focus)
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from)
+                            .collect(),
+                    );
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Removed { id },
+                } => {
+                    //Destroyed popups dismiss the popups grabbed above them.
+                    let stopped = self.seat_manager.popup_grab_remove(id).collect::<Vec<_>>();
+                    let focus_events = stopped
+                        .iter()
+                        .filter_map(|event| match event {
+                            SeatEvent::PopupGrabStopped {
+                                id,
+                                serial: _,
+                                surfaces,
+                                focus,
+                            } => Some(
+                                self.restore_popup_grab_focus(*id, surfaces, *focus)
+                                    .collect::<Vec<_>>(),
+                            ),
+                            _ => None,
+                        })
+                        .flatten()
+                        .collect::<Vec<_>>();
+                    let mut events = stopped
+                        .into_iter()
+                        .chain(focus_events.into_iter())
+                        .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                        .map(WCompEvent::from)
+                        .collect::<Vec<_>>();
+                    additional_events.append(&mut events);
+
+                    //Constraints of destroyed surfaces must not keep the cursor frozen.
+                    additional_events.append(
+                        &mut self
+                            .seat_manager
+                            .constraints_remove(id)
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from)
+                            .collect(),
+                    );
+
+                    //The tiling preview of a surface destroyed while dragged is hidden.
+                    additional_events.append(
+                        &mut self
+                            .surface_manager
+                            .set_tiling_preview(id, None)
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from)
+                            .collect(),
+                    );
+                }
                 WCompEvent::Seat {
                     serial: _,
                     event: SeatEvent::Cursor(CursorEvent::Moved { id, position }),
@@ -630,57 +1722,74 @@ impl GeometryManager {
                             .map(WCompEvent::from)
                             .collect(),
                     );
+                    additional_events.append(&mut self.follow_cursor_focus(id, focus));
+                    additional_events.append(&mut self.update_pointer_constraint(id));
+
+                    //The cursor crosses to any output containing its new position,
+                    //so that following relative positions refer to that output.
+                    let current_output = self
+                        .seat_manager
+                        .cursor_ref(id)
+                        .map(|cursor| cursor.output().clone())
+                        .flatten();
+                    let output = self
+                        .output_manager
+                        .get_output_at(&position)
+                        .map(|output| output.id);
+                    if let (Some(output_id), true) = (output, output != current_output) {
+                        let mut events = current_output
+                            .map(|current_output| {
+                                self.seat_manager
+                                    .left_cursor(id, current_output)
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or(Vec::new());
+                        events.append(&mut self.seat_manager.enter_cursor(id, output_id).collect());
+                        additional_events.append(
+                            &mut events
+                                .into_iter()
+                                .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                                .map(WCompEvent::from)
+                                .collect(),
+                        );
+                    }
                 }
                 WCompEvent::Output {
                     serial: _,
-                    event: OutputEvent::Removed { id: _ },
-                } => {
-                    /*
-                    let screen_size = self.output_manager.screen_size();
-                    let surfaces = self.surface_manager.surfaces_mut().filter_map(|surface| {
-                        if screen_size.contains(&surface.position) {
-                            Some(surface)
-                        } else {
-                            None
-                        }
-                    });
-                    */
-                    /*
-                    for surface in surfaces {
-                        if let Some(size) = surface.size().cloned() {
-                            surface.update_position(
-                                self.output_manager
-                                    .get_surface_optimal_position(&size),
-                            );
-                        };
-                        //TODO Missing propagate resize to client
-                    }
-                    */
+                    event: OutputEvent::Removed { .. },
                 }
-                WCompEvent::Output {
+                | WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Moved { .. },
+                }
+                | WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Resized { .. },
+                }
+                | WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Scaled { .. },
+                }
+                | WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Transformed { .. },
+                }
+                | WCompEvent::Output {
+                    serial: _,
+                    event: OutputEvent::Enabled { .. },
+                }
+                | WCompEvent::Output {
                     serial: _,
-                    event: OutputEvent::Moved { id: _, position: _ },
+                    event: OutputEvent::Disabled { .. },
                 } => {
-                    /*
-                    let surfaces = self.surface_manager.surfaces_mut().filter_map(|surface| {
-                        if old.geometry.contains(&surface.position) {
-                            Some(surface)
-                        } else {
-                            None
-                        }
-                    });
-                    */
-                    /*
-                    for surface in surfaces {
-                        if let Some(size) = surface.size().cloned() {
-                            surface.update_position(
-                                self.output_manager
-                                    .get_surface_optimal_position(&size),
-                            );
-                        }
-                        //TODO Missing propagate resize to client
-                    }
-                    */
+                    additional_events.append(
+                        &mut self
+                            .relocate_surfaces()
+                            .into_iter()
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from)
+                            .collect(),
+                    );
                 }
                 WCompEvent::Surface {
                     serial: _,
@@ -713,13 +1822,617 @@ impl GeometryManager {
 
                     additional_events.append(&mut events);
                 }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Fullscreened { id, output },
+                } => {
+                    let surface = self.surface_manager.surface_ref(id);
+                    let inner_offset = surface
+                        .map(|surface| surface.inner_geometry())
+                        .flatten()
+                        .map(|inner_geometry| {
+                            pal::Offset2D::from((
+                                inner_geometry.position.x,
+                                inner_geometry.position.y,
+                            ))
+                        })
+                        .unwrap_or(pal::Offset2D::from((0, 0)));
+                    let position = surface.map(|surface| surface.position()).cloned();
+
+                    //The requested output is honored if still present,
+                    //otherwise the output containing the surface is used.
+                    let output_geometry = output
+                        .map(|output_id| self.output_manager.output_ref(output_id))
+                        .flatten()
+                        .map(|(_index, output)| output.geometry.clone())
+                        .or_else(|| {
+                            position
+                                .map(|position| self.output_manager.get_output_at(&position))
+                                .flatten()
+                                .map(|output| output.geometry.clone())
+                        });
+
+                    let mut events = output_geometry
+                        .map(|output_geometry| {
+                            std::iter::empty()
+                                .chain(self.surface_manager.raise_surface(id))
+                                .chain(
+                                    self.surface_manager
+                                        .move_surface(id, output_geometry.position - inner_offset),
+                                )
+                                .chain(
+                                    self.surface_manager
+                                        .resize_surface(id, output_geometry.size),
+                                )
+                                .collect::<Vec<_>>()
+                        })
+                        .into_iter()
+                        .flatten()
+                        .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                        .map(WCompEvent::from)
+                        .collect::<Vec<_>>();
+
+                    additional_events.append(&mut events);
+                }
+                WCompEvent::Surface {
+                    serial: _,
+                    event: SurfaceEvent::Minimized { id },
+                } => {
+                    let mut events = self
+                        .seat_manager
+                        .keyboard_unfocus(id)
+                        .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                        .map(WCompEvent::from)
+                        .collect::<Vec<_>>();
+                    if self.surface_manager.active_surface() == Some(id) {
+                        events.append(
+                            &mut self
+                                .surface_manager
+                                .focus_surface(None)
+                                .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                                .map(WCompEvent::from)
+                                .collect(),
+                        );
+                    }
+                    additional_events.append(&mut events);
+                }
                 _ => (),
             }
             //self.events.push(event);
         });
 
+        //Parents moved to another output, directly or along with the output layout, bring their popups.
+        additional_events.append(
+            &mut self
+                .update_popup_bounds()
+                .into_iter()
+                .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                .map(WCompEvent::from)
+                .collect(),
+        );
+
+        //Focus changes and hidden surfaces can leave the cursor over another surface without moving it.
+        let refocus = events.clone().any(|event| match event {
+            WCompEvent::Seat {
+                serial: _,
+                event: SeatEvent::Keyboard(KeyboardEvent::Focus { .. }),
+            }
+            | WCompEvent::Surface {
+                serial: _,
+                event: SurfaceEvent::Removed { .. },
+            }
+            | WCompEvent::Surface {
+                serial: _,
+                event: SurfaceEvent::Minimized { .. },
+            }
+            | WCompEvent::Surface {
+                serial: _,
+                event: SurfaceEvent::BufferDetached { .. },
+            } => true,
+            _ => false,
+        });
+        if refocus {
+            additional_events.append(&mut self.refresh_cursors_focus());
+        }
+
+        additional_events.append(
+            &mut self
+                .update_surface_outputs()
+                .into_iter()
+                .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                .map(WCompEvent::from)
+                .collect(),
+        );
+
         events.chain(additional_events.into_iter())
     }
 
+    /// Track the output each surface is shown on, so that clients know which scale to render with.
+    /// A surface belongs to the output containing its center.
+    fn update_surface_outputs(&mut self) -> Vec<SurfaceEvent> {
+        let outputs = self
+            .surface_manager
+            .surfaces_ref()
+            .filter_map(|surface| {
+                let output = surface
+                    .geometry()
+                    .map(|geometry| {
+                        let center = pal::Position2D::from((
+                            geometry.position.x + geometry.size.width as i32 / 2,
+                            geometry.position.y + geometry.size.height as i32 / 2,
+                        ));
+                        self.output_manager
+                            .get_output_at(&center)
+                            .map(|output| output.id)
+                    })
+                    .flatten();
+                if surface.output() != output {
+                    Some((surface.id(), output))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        outputs
+            .into_iter()
+            .map(|(id, output)| {
+                self.surface_manager
+                    .set_surface_output(id, output)
+                    .collect::<Vec<_>>()
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Move the top-level surfaces along with the outputs they were shown on.
+    /// Surfaces are assigned to the previous layout before moving any of them,
+    /// so that outputs swapping places do not move them twice.
+    fn follow_moved_outputs(
+        &mut self,
+        moved: Vec<(usize, pal::Offset2D<i32>)>,
+    ) -> Vec<SurfaceEvent> {
+        //An output moved more than once keeps the sum of its displacements.
+        let mut offsets: Vec<(usize, pal::Offset2D<i32>)> = Vec::new();
+        for (id, offset) in moved {
+            match offsets.iter_mut().find(|(output, _)| *output == id) {
+                Some((_, total)) => {
+                    *total = pal::Offset2D::from((total.x + offset.x, total.y + offset.y))
+                }
+                None => offsets.push((id, offset)),
+            }
+        }
+        let previous_geometries = offsets
+            .into_iter()
+            .filter(|(_, offset)| offset.x != 0 || offset.y != 0)
+            .filter_map(|(id, offset)| {
+                let (_index, output) = self.output_manager.output_ref(id)?;
+                let geometry = pal::Rectangle::from((
+                    output.geometry.position.clone() - offset.clone(),
+                    output.geometry.size.clone(),
+                ));
+                Some((geometry, offset))
+            })
+            .collect::<Vec<_>>();
+        if previous_geometries.is_empty() {
+            return Vec::new();
+        }
+
+        let surfaces = self
+            .surface_manager
+            .toplevel_surfaces_ref()
+            .filter_map(|surface| {
+                let inner_geometry = surface.inner_geometry()?;
+                let inner_position = surface.position().clone() + inner_geometry.position.clone();
+                let center = pal::Position2D::from((
+                    inner_position.x + inner_geometry.size.width as i32 / 2,
+                    inner_position.y + inner_geometry.size.height as i32 / 2,
+                ));
+                let (_geometry, offset) = previous_geometries
+                    .iter()
+                    .find(|(geometry, _)| geometry.contains(&center))?;
+                let original = surface.state().map(|state| state.original()).flatten();
+                Some((
+                    surface.id(),
+                    surface.position().clone() + offset.clone(),
+                    original.map(|original| {
+                        pal::Rectangle::from((
+                            original.position.clone() + offset.clone(),
+                            original.size.clone(),
+                        ))
+                    }),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut events = Vec::new();
+        for (id, position, original) in surfaces {
+            if let Some(original) = original {
+                self.surface_manager.set_original_geometry(id, original);
+            }
+            events.append(&mut self.surface_manager.move_surface(id, position).collect());
+        }
+        events
+    }
+
+    /// Keep the popups inside the output containing their parent,
+    /// repositioning the ones whose bounds changed since they were last placed.
+    fn update_popup_bounds(&mut self) -> Vec<SurfaceEvent> {
+        let popups = self
+            .surface_manager
+            .surfaces_ref()
+            .filter_map(|surface| match surface.kind() {
+                SurfaceKind::Popup { state, .. } => Some((surface.id(), state.bounds.clone())),
+                SurfaceKind::Toplevel { .. } => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut parents = Vec::new();
+        for (id, bounds) in popups {
+            let parent = match self.surface_manager.surface_parent_ref(id) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let parent_id = parent.id();
+            let origin = parent
+                .inner_geometry()
+                .map(|inner_geometry| parent.position().clone() + inner_geometry.position.clone())
+                .unwrap_or(parent.position().clone());
+            let new_bounds = match self.output_manager.get_output_at(&origin) {
+                Some(output) => output.geometry.clone(),
+                None => continue,
+            };
+            if Some(&new_bounds) == bounds.as_ref() {
+                continue;
+            }
+            self.surface_manager.set_popup_bounds(id, new_bounds);
+            //Popups just added are placed once their size is known.
+            if bounds.is_some() && !parents.contains(&parent_id) {
+                parents.push(parent_id);
+            }
+        }
+        parents
+            .into_iter()
+            .filter_map(|parent| {
+                self.surface_manager
+                    .surface_mut(parent)
+                    .map(|parent| parent.update_children().collect::<Vec<_>>())
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Fit the top-level surfaces into the current output layout.
+    /// Surfaces left outside of any output are moved to the first one,
+    /// maximized and fullscreen surfaces are fitted again to their output
+    /// and surfaces larger than their output are shrunk.
+    fn relocate_surfaces(&mut self) -> Vec<SurfaceEvent> {
+        let fallback = match self.output_manager.outputs_ref().next() {
+            Some(output) => output.geometry.clone(),
+            None => return Vec::new(),
+        };
+        let surfaces = self
+            .surface_manager
+            .toplevel_surfaces_ref()
+            .filter_map(|surface| {
+                let inner_geometry = surface.inner_geometry()?.clone();
+                let altered = surface
+                    .state()
+                    .map(|state| state.is_maximized() || state.is_fullscreen())
+                    == Some(true);
+                let original = surface
+                    .state()
+                    .map(|state| state.original())
+                    .flatten()
+                    .cloned();
+                Some((
+                    surface.id(),
+                    surface.position().clone(),
+                    inner_geometry,
+                    altered,
+                    original,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut events = Vec::new();
+        for (id, position, inner_geometry, altered, original) in surfaces {
+            let inner_position = position.clone() + inner_geometry.position.clone();
+            let center = pal::Position2D::from((
+                inner_position.x + inner_geometry.size.width as i32 / 2,
+                inner_position.y + inner_geometry.size.height as i32 / 2,
+            ));
+            let output = self
+                .output_manager
+                .get_output_at(&center)
+                .or_else(|| self.output_manager.get_output_at(&inner_position))
+                .map(|output| output.geometry.clone());
+            let orphan = output.is_none();
+            let output = output.unwrap_or(fallback.clone());
+            let inner_offset =
+                pal::Offset2D::from((inner_geometry.position.x, inner_geometry.position.y));
+
+            if altered {
+                //The geometry restored later is centered on the new output if its one is gone.
+                let original = original.filter(|original| {
+                    let center = pal::Position2D::from((
+                        original.position.x + original.size.width as i32 / 2,
+                        original.position.y + original.size.height as i32 / 2,
+                    ));
+                    self.output_manager.get_output_at(&center).is_none()
+                });
+                if let Some(original) = original {
+                    let size = pal::Size2D::from((
+                        original.size.width.min(output.size.width),
+                        original.size.height.min(output.size.height),
+                    ));
+                    let position = pal::Position2D::from((
+                        output.position.x + (output.size.width - size.width) as i32 / 2,
+                        output.position.y + (output.size.height - size.height) as i32 / 2,
+                    ));
+                    self.surface_manager
+                        .set_original_geometry(id, pal::Rectangle::from((position, size)));
+                }
+                events.append(
+                    &mut self
+                        .surface_manager
+                        .move_surface(id, output.position.clone() - inner_offset)
+                        .chain(self.surface_manager.resize_surface(id, output.size.clone()))
+                        .collect(),
+                );
+                continue;
+            }
+
+            let size = pal::Size2D::from((
+                inner_geometry.size.width.min(output.size.width),
+                inner_geometry.size.height.min(output.size.height),
+            ));
+            if size != inner_geometry.size {
+                events.append(
+                    &mut self
+                        .surface_manager
+                        .resize_surface(id, size.clone())
+                        .collect(),
+                );
+            }
+
+            //Orphans are centered on the new output, the others are just kept inside their one.
+            let (x, y) = if orphan {
+                (
+                    output.position.x + (output.size.width - size.width) as i32 / 2,
+                    output.position.y + (output.size.height - size.height) as i32 / 2,
+                )
+            } else {
+                (
+                    inner_position
+                        .x
+                        .max(output.position.x)
+                        .min(output.position.x + (output.size.width - size.width) as i32),
+                    inner_position
+                        .y
+                        .max(output.position.y)
+                        .min(output.position.y + (output.size.height - size.height) as i32),
+                )
+            };
+            let new_position = pal::Position2D::from((x, y)) - inner_offset;
+            if new_position != position {
+                events.append(
+                    &mut self
+                        .surface_manager
+                        .move_surface(id, new_position)
+                        .collect(),
+                );
+            }
+        }
+        events
+    }
+
+    /// Move the keyboard focus to the surface under the cursor, according to the focus policy.
+    /// The focus is left untouched while a popup grab or an interactive operation is in progress.
+    fn follow_cursor_focus(&mut self, id: usize, surface: Option<usize>) -> Vec<WCompEvent> {
+        let surface = match (self.focus_policy, surface) {
+            (FocusPolicy::Click, _) => return Vec::new(),
+            (FocusPolicy::Sloppy, None) => return Vec::new(),
+            (_, surface) => surface,
+        };
+        if self.seat_manager.popup_grab_ref(id).is_some() {
+            return Vec::new();
+        }
+        let busy = self
+            .surface_manager
+            .active_surface()
+            .map(|active| self.surface_manager.surface_ref(active))
+            .flatten()
+            .map(|active| active.state())
+            .flatten()
+            .map(|state| state.is_moving() || state.is_resizing())
+            == Some(true);
+        let focused = self
+            .seat_manager
+            .keyboard_ref(id)
+            .map(|keyboard| keyboard.focus().clone());
+        match focused {
+            Some(focused) if !busy && focused != surface => {
+                //Additional events are not postprocessed, so the toplevel activation is done here.
+                let toplevel = surface
+                    .map(|surface| self.surface_manager.toplevel_of(surface))
+                    .flatten();
+                self.seat_manager
+                    .keyboard_focus(id, surface)
+                    .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                    .map(WCompEvent::from)
+                    .chain(
+                        self.surface_manager
+                            .focus_surface(toplevel)
+                            .map(|event| (ews::SERIAL_COUNTER.next_serial().into(), event))
+                            .map(WCompEvent::from),
+                    )
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Give back the keyboard focus if it was held by one of the dismissed popups.
+    fn restore_popup_grab_focus(
+        &mut self,
+        id: usize,
+        surfaces: &[usize],
+        focus: Option<usize>,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        let focused = self
+            .seat_manager
+            .keyboard_ref(id)
+            .map(|keyboard| keyboard.focus().clone())
+            .flatten();
+        if focused.map(|focused| surfaces.contains(&focused)) == Some(true) {
+            self.seat_manager
+                .keyboard_focus(id, focus)
+                .collect::<Vec<_>>()
+                .into_iter()
+        } else {
+            Vec::new().into_iter()
+        }
+    }
+
     fn reposition_surfaces(&mut self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry_manager(placement_strategy: PlacementStrategy) -> GeometryManager {
+        let config = Config {
+            keyboards: KeyboardConfig::default(),
+            output_profiles: OutputProfiles::load(None),
+            settings: Settings {
+                placement_strategy: Some(placement_strategy),
+                ..Settings::default()
+            },
+        };
+        GeometryManager::new(config)
+    }
+
+    fn bounds() -> pal::Rectangle<i32, u32> {
+        pal::Rectangle::from((
+            pal::Position2D::from((100, 50)),
+            pal::Size2D::from((800, 600)),
+        ))
+    }
+
+    fn position(position: pal::Position2D<i32>) -> (i32, i32) {
+        (position.x, position.y)
+    }
+
+    #[test]
+    fn clamp_into_keeps_surfaces_inside_the_bounds() {
+        let size = pal::Size2D::from((200, 100));
+        let clamp = |x, y| {
+            position(GeometryManager::clamp_into(
+                pal::Position2D::from((x, y)),
+                &size,
+                &bounds(),
+            ))
+        };
+        assert_eq!(clamp(300, 200), (300, 200));
+        assert_eq!(clamp(0, 0), (100, 50));
+        assert_eq!(clamp(850, 600), (700, 550));
+    }
+
+    #[test]
+    fn clamp_into_aligns_bigger_surfaces_to_the_bounds_origin() {
+        let size = pal::Size2D::from((1000, 1000));
+        assert_eq!(
+            position(GeometryManager::clamp_into(
+                pal::Position2D::from((300, 200)),
+                &size,
+                &bounds()
+            )),
+            (100, 50)
+        );
+    }
+
+    #[test]
+    fn cascade_starts_from_the_bounds_corner() {
+        let geometry_manager = geometry_manager(PlacementStrategy::Cascade);
+        let cascade = |size| position(geometry_manager.cascade_position(&bounds(), &size));
+        assert_eq!(cascade(pal::Size2D::from((200, 100))), (132, 82));
+        //Surfaces not fitting once shifted are placed in the corner.
+        assert_eq!(cascade(pal::Size2D::from((790, 100))), (100, 50));
+    }
+
+    #[test]
+    fn smart_placement_without_surfaces_uses_the_bounds_corner() {
+        let geometry_manager = geometry_manager(PlacementStrategy::Smart);
+        assert_eq!(
+            position(geometry_manager.smart_position(&bounds(), &pal::Size2D::from((200, 100)))),
+            (100, 50)
+        );
+    }
+
+    #[test]
+    fn under_cursor_placement_needs_a_cursor() {
+        let geometry_manager = geometry_manager(PlacementStrategy::UnderCursor);
+        assert!(geometry_manager
+            .under_cursor_position(&pal::Size2D::from((200, 100)))
+            .is_none());
+    }
+
+    #[test]
+    fn optimal_position_without_outputs_is_the_origin() {
+        for placement_strategy in vec![
+            PlacementStrategy::Centered,
+            PlacementStrategy::Cascade,
+            PlacementStrategy::UnderCursor,
+            PlacementStrategy::Smart,
+        ] {
+            let geometry_manager = geometry_manager(placement_strategy);
+            let (optimal, _depth) =
+                geometry_manager.get_surface_optimal_position(&pal::Size2D::from((200, 100)));
+            assert_eq!(position(optimal), (0, 0));
+        }
+    }
+
+    #[test]
+    fn clamp_to_rectangles_keeps_positions_inside() {
+        let rectangles = vec![bounds()];
+        let clamped = GeometryManager::clamp_to_rectangles(
+            pal::Position2D::from((899, 649)),
+            rectangles.iter(),
+        );
+        assert_eq!(clamped.map(position), Some((899, 649)));
+    }
+
+    #[test]
+    fn clamp_to_rectangles_picks_the_closest_rectangle() {
+        //Two outputs side by side, the second one shorter and lower.
+        let rectangles = vec![
+            pal::Rectangle::from((pal::Position2D::from((0, 0)), pal::Size2D::from((100, 100)))),
+            pal::Rectangle::from((
+                pal::Position2D::from((100, 50)),
+                pal::Size2D::from((100, 100)),
+            )),
+        ];
+        let clamp = |x, y| {
+            GeometryManager::clamp_to_rectangles(pal::Position2D::from((x, y)), rectangles.iter())
+                .map(position)
+        };
+        //The last pixel of the layout is the last one of each rectangle.
+        assert_eq!(clamp(250, 10), Some((199, 50)));
+        assert_eq!(clamp(150, 10), Some((150, 50)));
+        assert_eq!(clamp(-20, 120), Some((0, 99)));
+        assert_eq!(clamp(120, 200), Some((120, 149)));
+    }
+
+    #[test]
+    fn clamp_to_rectangles_without_rectangles() {
+        assert_eq!(
+            GeometryManager::clamp_to_rectangles(
+                pal::Position2D::from((10, 10)),
+                std::iter::empty()
+            )
+            .map(position),
+            None
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! [OutputManager][OutputManager] related structures and enumerations.
 
+use super::{OutputProfile, OutputProfiles, OutputSettings};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -7,6 +9,7 @@ use std::sync::Arc;
 pub enum OutputRequest {
     Added {
         id: usize,
+        name: String,
         handle: Arc<pal::wgpu::Surface>,
         size: pal::Size2D<u32>,
     },
@@ -18,8 +21,22 @@ pub enum OutputRequest {
         size: pal::Size2D<u32>,
     },
     Moved {
-        old: Output,
-        new_position: pal::Position2D<i32>,
+        id: usize,
+        position: pal::Position2D<i32>,
+    },
+    Scaled {
+        id: usize,
+        scale: f64,
+    },
+    Transformed {
+        id: usize,
+        transform: OutputTransform,
+    },
+    Enabled {
+        id: usize,
+    },
+    Disabled {
+        id: usize,
     },
 }
 
@@ -41,71 +58,251 @@ pub enum OutputEvent {
     Moved {
         id: usize,
         position: pal::Position2D<i32>,
+        /// Displacement from the previous position, zero for outputs just placed in the layout.
+        offset: pal::Offset2D<i32>,
+    },
+    Scaled {
+        id: usize,
+        scale: f64,
+    },
+    Transformed {
+        id: usize,
+        transform: OutputTransform,
+    },
+    Enabled {
+        id: usize,
+        handle: Arc<pal::wgpu::Surface>,
+        size: pal::Size2D<u32>,
+    },
+    Disabled {
+        id: usize,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Rotation and flipping of an output, following the wl_output semantics:
+/// rotations are counter-clockwise and flipping happens around the vertical axis before rotating.
+pub enum OutputTransform {
+    Normal,
+    Rotated90,
+    Rotated180,
+    Rotated270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+impl OutputTransform {
+    /// Check if the transform swaps the width and the height of the output.
+    pub fn is_rotated(&self) -> bool {
+        match self {
+            Self::Rotated90 | Self::Rotated270 | Self::Flipped90 | Self::Flipped270 => true,
+            _ => false,
+        }
+    }
+
+    /// Apply the transform to a size.
+    pub fn apply_size(&self, size: &pal::Size2D<u32>) -> pal::Size2D<u32> {
+        if self.is_rotated() {
+            pal::Size2D::from((size.height, size.width))
+        } else {
+            size.clone()
+        }
+    }
+
+    /// Map a position on the panel of `size` pixels to the transformed output.
+    pub fn apply_position(
+        &self,
+        position: &pal::Position2D<i32>,
+        size: &pal::Size2D<u32>,
+    ) -> pal::Position2D<i32> {
+        let (x, y) = (position.x, position.y);
+        let (width, height) = (size.width as i32, size.height as i32);
+        let (x, y) = match self {
+            Self::Normal => (x, y),
+            Self::Rotated90 => (height - 1 - y, x),
+            Self::Rotated180 => (width - 1 - x, height - 1 - y),
+            Self::Rotated270 => (y, width - 1 - x),
+            Self::Flipped => (width - 1 - x, y),
+            Self::Flipped90 => (height - 1 - y, width - 1 - x),
+            Self::Flipped180 => (x, height - 1 - y),
+            Self::Flipped270 => (y, x),
+        };
+        pal::Position2D::from((x, y))
+    }
+}
+impl Default for OutputTransform {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
 #[derive(Debug, Clone)]
 /// Representation of an output.
+/// The geometry is expressed in logical coordinates, that is the physical size,
+/// rotated by the transform and divided by the scale.
 pub struct Output {
     pub id: usize,
+    pub name: String,
+    pub handle: Arc<pal::wgpu::Surface>,
     pub geometry: pal::Rectangle<i32, u32>,
+    pub physical_size: pal::Size2D<u32>,
+    pub scale: f64,
+    pub transform: OutputTransform,
 }
 impl Output {
-    pub fn new(id: usize, geometry: impl Into<pal::Rectangle<i32, u32>>) -> Self {
+    pub fn new(
+        id: usize,
+        name: String,
+        handle: Arc<pal::wgpu::Surface>,
+        geometry: impl Into<pal::Rectangle<i32, u32>>,
+    ) -> Self {
         let geometry = geometry.into();
-        Self { id, geometry }
+        let physical_size = geometry.size.clone();
+        let scale = 1.0;
+        let transform = OutputTransform::default();
+        Self {
+            id,
+            name,
+            handle,
+            geometry,
+            physical_size,
+            scale,
+            transform,
+        }
+    }
+
+    /// Set the physical size of the output, updating its logical size accordingly.
+    pub fn set_physical_size(&mut self, size: pal::Size2D<u32>) {
+        self.physical_size = size;
+        self.update_logical_size();
+    }
+
+    /// Set the scale of the output, updating its logical size accordingly.
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+        self.update_logical_size();
+    }
+
+    /// Set the transform of the output, updating its logical size accordingly.
+    pub fn set_transform(&mut self, transform: OutputTransform) {
+        self.transform = transform;
+        self.update_logical_size();
+    }
+
+    fn update_logical_size(&mut self) {
+        let size = self.transform.apply_size(&self.physical_size);
+        self.geometry.size = pal::Size2D::from((
+            (size.width as f64 / self.scale).round() as u32,
+            (size.height as f64 / self.scale).round() as u32,
+        ));
+    }
+
+    fn settings(&self, enabled: bool) -> OutputSettings {
+        OutputSettings {
+            position: self.geometry.position.clone(),
+            scale: self.scale,
+            transform: self.transform,
+            enabled,
+        }
     }
 }
 
 #[derive(Debug)]
 /// Component responsible to handle the outputs.
+/// Disabled outputs are kept aside, out of the layout, until they get enabled again.
 pub struct OutputManager {
     outputs: Vec<Output>,
+    disabled: Vec<Output>,
+    profiles: OutputProfiles,
 }
 impl OutputManager {
-    pub fn new() -> Self {
+    pub fn new(profiles: OutputProfiles) -> Self {
         let outputs = Vec::new();
-        Self { outputs }
+        let disabled = Vec::new();
+        Self {
+            outputs,
+            disabled,
+            profiles,
+        }
     }
 
+    /// Add an output to the layout.
+    /// If a profile exists for the new set of connected outputs, it is applied.
     pub fn add_output(
         &mut self,
         id: usize,
+        name: String,
         handle: std::sync::Arc<pal::wgpu::Surface>,
         size: pal::Size2D<u32>,
     ) -> impl Iterator<Item = OutputEvent> + Clone {
-        let x_offset = *self
+        //New outputs are placed on the right of the layout, where they cannot overlap others.
+        let x_offset = self
             .outputs
-            .last()
+            .iter()
             .map(|output| output.geometry.x_offset())
-            .get_or_insert(0);
+            .max()
+            .unwrap_or(0);
         let position = pal::Position2D::from((x_offset, 0));
-        let output = Output::new(id.clone(), (position.clone(), size.clone()));
+        let output = Output::new(
+            id.clone(),
+            name,
+            handle.clone(),
+            (position.clone(), size.clone()),
+        );
         self.outputs.push(output);
-        vec![
+        let offset = pal::Offset2D::from((0, 0));
+        let mut events = vec![
             OutputEvent::Added { id, handle, size },
-            OutputEvent::Moved { id, position },
-        ]
-        .into_iter()
+            OutputEvent::Moved {
+                id,
+                position,
+                offset,
+            },
+        ];
+        events.append(&mut self.apply_profile());
+        events.into_iter()
     }
+    /// Remove an output from the layout.
+    /// If a profile exists for the remaining set of connected outputs, it is applied.
     pub fn del_output(&mut self, id: usize) -> impl Iterator<Item = OutputEvent> + Clone {
-        let indexes_to_update = self
+        //A disabled output is already out of the layout and of the renderer.
+        if let Some(position) = self.disabled.iter().position(|output| output.id == id) {
+            self.disabled.remove(position);
+            return self.apply_profile().into_iter();
+        }
+        //The remaining outputs keep their position in the layout.
+        let mut events = self
             .outputs
             .iter()
             .position(|output| output.id == id)
             .map(|position| {
                 self.outputs.remove(position);
-                position..self.outputs.len()
-            });
-        indexes_to_update.map(|range|{
-            std::iter::once(OutputEvent::Removed { id })
-            .chain(self.update_offset(range))
-        }).into_iter().flatten()
-        /*
-            .get_or_insert(0..0)
-            .clone();
-        std::iter::once()
-        */
+                OutputEvent::Removed { id }
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        if !events.is_empty() {
+            events.append(&mut self.apply_profile());
+        }
+        events.into_iter()
+    }
+    /// Enable a disabled output, putting it back in the layout at its previous position.
+    pub fn enable_output(&mut self, id: usize) -> impl Iterator<Item = OutputEvent> + Clone {
+        let events = self.enable(id);
+        if !events.is_empty() {
+            self.store_profile();
+        }
+        events.into_iter()
+    }
+    /// Disable an output, removing it from the layout.
+    /// The last enabled output cannot be disabled.
+    pub fn disable_output(&mut self, id: usize) -> impl Iterator<Item = OutputEvent> + Clone {
+        let events = self.disable(id);
+        if !events.is_empty() {
+            self.store_profile();
+        }
+        events.into_iter()
     }
     pub fn resize_output(
         &mut self,
@@ -118,22 +315,111 @@ impl OutputManager {
                     id: output.id,
                     size: size.clone(),
                 };
-                output.geometry.size = size;
+                output.set_physical_size(size);
 
                 let mut events = vec![event];
-                events.append(&mut self.update_offset(index..self.outputs.len()).collect());
+                events.append(&mut self.resolve_overlaps(index));
                 events.into_iter()
             }
             None => Vec::new().into_iter(),
         }
     }
+    /// Move an output to an arbitrary position of the layout.
+    /// The request is refused if the output would overlap another one.
+    pub fn move_output(
+        &mut self,
+        id: usize,
+        position: pal::Position2D<i32>,
+    ) -> impl Iterator<Item = OutputEvent> + Clone {
+        let geometry = match self.output_ref(id) {
+            Some((_index, output)) if output.geometry.position != position => {
+                pal::Rectangle::from((position.clone(), output.geometry.size.clone()))
+            }
+            _ => return Vec::new().into_iter(),
+        };
+        let overlapping = self
+            .outputs
+            .iter()
+            .find(|output| output.id != id && Self::overlaps(&output.geometry, &geometry));
+        if let Some(overlapping) = overlapping {
+            log::error!(target: "Output Manager","Output {} cannot be moved to {:?}: it would overlap output {}",id,position,overlapping.id);
+            return Vec::new().into_iter();
+        }
+        let events = self
+            .output_mut(id)
+            .map(|(_index, output)| {
+                let offset = Self::offset(&output.geometry.position, &position);
+                output.geometry.position = position.clone();
+                OutputEvent::Moved {
+                    id,
+                    position,
+                    offset,
+                }
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        if !events.is_empty() {
+            self.store_profile();
+        }
+        events.into_iter()
+    }
 
+    /// Change the scale of an output.
+    /// Its logical size changes accordingly, so the outputs it overlaps afterwards are pushed away.
+    pub fn scale_output(
+        &mut self,
+        id: usize,
+        scale: f64,
+    ) -> impl Iterator<Item = OutputEvent> + Clone {
+        if !scale.is_finite() || scale <= 0.0 {
+            log::error!(target: "Output Manager","Invalid scale {} for output {}",scale,id);
+            return Vec::new().into_iter();
+        }
+        match self.output_mut(id) {
+            Some((index, output)) if output.scale != scale => {
+                output.set_scale(scale);
+                let mut events = vec![OutputEvent::Scaled { id, scale }];
+                events.append(&mut self.resolve_overlaps(index));
+                self.store_profile();
+                events.into_iter()
+            }
+            _ => Vec::new().into_iter(),
+        }
+    }
+
+    /// Change the transform of an output.
+    /// Rotations swap its logical size, so the outputs it overlaps afterwards are pushed away.
+    pub fn transform_output(
+        &mut self,
+        id: usize,
+        transform: OutputTransform,
+    ) -> impl Iterator<Item = OutputEvent> + Clone {
+        match self.output_mut(id) {
+            Some((index, output)) if output.transform != transform => {
+                output.set_transform(transform);
+                let mut events = vec![OutputEvent::Transformed { id, transform }];
+                events.append(&mut self.resolve_overlaps(index));
+                self.store_profile();
+                events.into_iter()
+            }
+            _ => Vec::new().into_iter(),
+        }
+    }
+
+    /// Transform a physical position relative to an output into an absolute logical position.
     pub fn relative_to_absolute(
         &self,
         id: usize,
         position: pal::Position2D<i32>,
     ) -> Option<pal::Position2D<i32>> {
         if let Some((_index, output)) = self.output_ref(id) {
+            let position = output
+                .transform
+                .apply_position(&position, &output.physical_size);
+            let position = pal::Position2D::from((
+                (position.x as f64 / output.scale).round() as i32,
+                (position.y as f64 / output.scale).round() as i32,
+            ));
             Some(output.geometry.position.clone() + position)
         } else {
             log::error!(target: "WComp","Cannot get the absolute position from output the relative one");
@@ -141,20 +427,87 @@ impl OutputManager {
         }
     }
 
+    /// Get the bounding rectangle of all the outputs.
     pub fn screen_size(&self) -> pal::Rectangle<i32, u32> {
-        self.outputs_ref()
-            .last()
-            .map(|last_output| {
-                let position = pal::Position2D::from((0i32, 0i32));
-                let size = {
-                    let mut size = last_output.geometry.size.clone();
-                    size.width += last_output.geometry.position.x as u32;
-                    size.width += last_output.geometry.position.x as u32;
-                    size
-                };
-                pal::Rectangle { position, size }
+        let min_x = self
+            .outputs
+            .iter()
+            .map(|output| output.geometry.position.x)
+            .min();
+        let min_y = self
+            .outputs
+            .iter()
+            .map(|output| output.geometry.position.y)
+            .min();
+        let max_x = self
+            .outputs
+            .iter()
+            .map(|output| output.geometry.x_offset())
+            .max();
+        let max_y = self
+            .outputs
+            .iter()
+            .map(|output| output.geometry.position.y + output.geometry.size.height as i32)
+            .max();
+        match (min_x, min_y, max_x, max_y) {
+            (Some(min_x), Some(min_y), Some(max_x), Some(max_y)) => pal::Rectangle::from((
+                pal::Position2D::from((min_x, min_y)),
+                pal::Size2D::from(((max_x - min_x) as u32, (max_y - min_y) as u32)),
+            )),
+            _ => pal::Rectangle::from((
+                pal::Position2D::from((0i32, 0i32)),
+                pal::Size2D::from((0u32, 0u32)),
+            )),
+        }
+    }
+
+    /// Get the scale used to lay out the outputs in the physical space of the renderer.
+    /// Using the biggest scale guarantees that outputs not overlapping in the logical space
+    /// do not overlap in the physical space either.
+    pub fn layout_scale(&self) -> f64 {
+        self.outputs
+            .iter()
+            .map(|output| output.scale)
+            .fold(None, |max: Option<f64>, scale| {
+                Some(max.map(|max| max.max(scale)).unwrap_or(scale))
             })
-            .unwrap()
+            .unwrap_or(1.0)
+    }
+
+    /// Get the position of an output in the physical space of the renderer.
+    pub fn physical_position(&self, output: &Output) -> pal::Position2D<i32> {
+        let layout_scale = self.layout_scale();
+        pal::Position2D::from((
+            (output.geometry.position.x as f64 * layout_scale).round() as i32,
+            (output.geometry.position.y as f64 * layout_scale).round() as i32,
+        ))
+    }
+
+    /// Transform a logical rectangle into the physical space of the renderer,
+    /// using the scale of the output containing its origin.
+    pub fn to_physical(&self, geometry: &pal::Rectangle<i32, u32>) -> pal::Rectangle<i32, u32> {
+        let output = self
+            .get_output_at(&geometry.position)
+            .or(self.outputs.first());
+        match output {
+            Some(output) => {
+                let origin = self.physical_position(output);
+                let position = pal::Position2D::from((
+                    origin.x
+                        + ((geometry.position.x - output.geometry.position.x) as f64 * output.scale)
+                            .round() as i32,
+                    origin.y
+                        + ((geometry.position.y - output.geometry.position.y) as f64 * output.scale)
+                            .round() as i32,
+                ));
+                let size = pal::Size2D::from((
+                    (geometry.size.width as f64 * output.scale).round() as u32,
+                    (geometry.size.height as f64 * output.scale).round() as u32,
+                ));
+                pal::Rectangle::from((position, size))
+            }
+            None => geometry.clone(),
+        }
     }
 
     pub fn output_ref(&self, id: usize) -> Option<(usize, &Output)> {
@@ -189,35 +542,177 @@ impl OutputManager {
         self.outputs.iter_mut()
     }
 
-    fn update_offset<'a>(
-        &'a mut self,
-        range: impl std::ops::RangeBounds<usize> + Iterator<Item = usize>,
-    ) -> impl Iterator<Item = OutputEvent> + Clone {
-        let mut events = Vec::new();
-        for index in range {
-            let x_offset = if index == 0 {
-                0
-            } else {
-                self.outputs[index - 1].geometry.x_offset()
-            };
+    fn enable(&mut self, id: usize) -> Vec<OutputEvent> {
+        let output = match self.disabled.iter().position(|output| output.id == id) {
+            Some(position) => self.disabled.remove(position),
+            None => return Vec::new(),
+        };
+        let handle = output.handle.clone();
+        let size = output.physical_size.clone();
+        let position = output.geometry.position.clone();
+        self.outputs.push(output);
+        let offset = pal::Offset2D::from((0, 0));
+        let mut events = vec![
+            OutputEvent::Enabled { id, handle, size },
+            OutputEvent::Moved {
+                id,
+                position,
+                offset,
+            },
+        ];
+        events.append(&mut self.resolve_overlaps(self.outputs.len() - 1));
+        events
+    }
 
-            if self.outputs[index].geometry.position.x == x_offset {
-                return events.into_iter();
-            } else {
-                let id = self.outputs[index].id;
-                self.outputs[index].geometry.position.x = x_offset;
-                let position = self.outputs[index].geometry.position.clone();
-                let event = OutputEvent::Moved { id, position };
-                events.push(event);
+    fn disable(&mut self, id: usize) -> Vec<OutputEvent> {
+        match self.outputs.iter().position(|output| output.id == id) {
+            Some(_) if self.outputs.len() == 1 => {
+                log::error!(target: "Output Manager","Output {} cannot be disabled: it is the last enabled one",id);
+                Vec::new()
+            }
+            Some(position) => {
+                let output = self.outputs.remove(position);
+                self.disabled.push(output);
+                vec![OutputEvent::Disabled { id }]
             }
+            None => Vec::new(),
         }
-        events.into_iter()
     }
 
-    pub fn get_surface_optimal_size(&self) -> pal::Size2D<u32> {
+    fn connected_names(&self) -> BTreeSet<String> {
         self.outputs
             .iter()
-            .next()
+            .chain(self.disabled.iter())
+            .map(|output| output.name.clone())
+            .collect()
+    }
+
+    /// Save the current layout as the profile of the connected set of outputs.
+    fn store_profile(&mut self) {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| (output.name.clone(), output.settings(true)))
+            .chain(
+                self.disabled
+                    .iter()
+                    .map(|output| (output.name.clone(), output.settings(false))),
+            )
+            .collect();
+        self.profiles.store(OutputProfile { outputs });
+    }
+
+    /// Apply the profile of the connected set of outputs, if any.
+    /// Profiles are trusted to be overlap free, since they are saved from valid layouts.
+    fn apply_profile(&mut self) -> Vec<OutputEvent> {
+        let profile = match self.profiles.find(&self.connected_names()) {
+            Some(profile) => profile.clone(),
+            None => return Vec::new(),
+        };
+        let id_of = |outputs: &Vec<Output>, name: &String| {
+            outputs
+                .iter()
+                .find(|output| &output.name == name)
+                .map(|output| output.id)
+        };
+
+        let mut events = Vec::new();
+        for (name, settings) in profile.outputs.iter() {
+            if let (true, Some(id)) = (settings.enabled, id_of(&self.disabled, name)) {
+                events.append(&mut self.enable(id));
+            }
+        }
+        for (name, settings) in profile.outputs.iter() {
+            let output = match self.outputs.iter_mut().find(|output| &output.name == name) {
+                Some(output) => output,
+                None => continue,
+            };
+            let id = output.id;
+            if output.scale != settings.scale {
+                output.set_scale(settings.scale);
+                events.push(OutputEvent::Scaled {
+                    id,
+                    scale: settings.scale,
+                });
+            }
+            if output.transform != settings.transform {
+                output.set_transform(settings.transform);
+                events.push(OutputEvent::Transformed {
+                    id,
+                    transform: settings.transform,
+                });
+            }
+            if output.geometry.position != settings.position {
+                let offset = Self::offset(&output.geometry.position, &settings.position);
+                output.geometry.position = settings.position.clone();
+                events.push(OutputEvent::Moved {
+                    id,
+                    position: settings.position.clone(),
+                    offset,
+                });
+            }
+        }
+        for (name, settings) in profile.outputs.iter() {
+            if let (false, Some(id)) = (settings.enabled, id_of(&self.outputs, name)) {
+                events.append(&mut self.disable(id));
+            }
+        }
+        events
+    }
+
+    fn offset(from: &pal::Position2D<i32>, to: &pal::Position2D<i32>) -> pal::Offset2D<i32> {
+        pal::Offset2D::from((to.x - from.x, to.y - from.y))
+    }
+
+    fn overlaps(first: &pal::Rectangle<i32, u32>, second: &pal::Rectangle<i32, u32>) -> bool {
+        first.position.x < second.x_offset()
+            && second.position.x < first.x_offset()
+            && first.position.y < second.position.y + second.size.height as i32
+            && second.position.y < first.position.y + first.size.height as i32
+    }
+
+    /// Push on the right the outputs overlapped by the output at `index`,
+    /// propagating the move to the outputs they overlap in turn.
+    fn resolve_overlaps(&mut self, index: usize) -> Vec<OutputEvent> {
+        let geometry = self.outputs[index].geometry.clone();
+        let overlapped = (0..self.outputs.len())
+            .filter(|other| {
+                *other != index && Self::overlaps(&self.outputs[*other].geometry, &geometry)
+            })
+            .collect::<Vec<_>>();
+        let mut events = Vec::new();
+        for other in overlapped {
+            //A previous push may have already moved it out of the way
+            if !Self::overlaps(&self.outputs[other].geometry, &geometry) {
+                continue;
+            }
+            let id = self.outputs[other].id;
+            let offset = pal::Offset2D::from((
+                geometry.x_offset() - self.outputs[other].geometry.position.x,
+                0,
+            ));
+            self.outputs[other].geometry.position.x = geometry.x_offset();
+            let position = self.outputs[other].geometry.position.clone();
+            events.push(OutputEvent::Moved {
+                id,
+                position,
+                offset,
+            });
+            events.append(&mut self.resolve_overlaps(other));
+        }
+        events
+    }
+
+    /// Get the output with the given id, falling back to the first one.
+    fn output_or_first(&self, id: Option<usize>) -> Option<&Output> {
+        id.map(|id| self.outputs.iter().find(|output| output.id == id))
+            .flatten()
+            .or(self.outputs.first())
+    }
+
+    /// Get the optimal size for a new surface on `output`, or on the first output if missing.
+    pub fn get_surface_optimal_size(&self, output: Option<usize>) -> pal::Size2D<u32> {
+        self.output_or_first(output)
             .map(|output| {
                 pal::Size2D::from([
                     output.geometry.size.width / 2,
@@ -227,20 +722,23 @@ impl OutputManager {
             .unwrap_or(pal::Size2D::from([200, 200]))
     }
 
-    pub fn get_surface_optimal_position(&self, size: &pal::Size2D<u32>) -> pal::Position2D<i32> {
-        self.outputs
-            .iter()
-            .next()
+    /// Get the position centering a surface of `size` on `output`, or on the first output if missing.
+    pub fn get_surface_optimal_position(
+        &self,
+        output: Option<usize>,
+        size: &pal::Size2D<u32>,
+    ) -> pal::Position2D<i32> {
+        self.output_or_first(output)
             .map(|output| {
-                let mut x = (output.geometry.size.width / 2) as i32 - size.width as i32;
+                let mut x = (output.geometry.size.width as i32 - size.width as i32) / 2;
                 if x < 0 {
                     x = 0;
                 }
-                let mut y = (output.geometry.size.height / 2) as i32 - size.height as i32;
+                let mut y = (output.geometry.size.height as i32 - size.height as i32) / 2;
                 if y < 0 {
                     y = 0;
                 }
-                pal::Position2D::from([x, y])
+                output.geometry.position.clone() + pal::Position2D::from([x, y])
             })
             .unwrap_or(pal::Position2D::from([0, 0]))
     }
@@ -0,0 +1,326 @@
+//! [OutputProfiles][OutputProfiles] related structures.
+//!
+//! Profiles are stored in a plain text file, one `[profile]` section per set of outputs
+//! and one line per output, identified by its connector name:
+//! ```text
+//! [profile]
+//! eDP-1 = position:0,0 scale:2 transform:normal enabled:true
+//! HDMI-A-1 = position:1920,0 scale:1 transform:90 enabled:true
+//! ```
+
+use super::OutputTransform;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Settings of a single output inside a profile.
+pub struct OutputSettings {
+    pub position: pal::Position2D<i32>,
+    pub scale: f64,
+    pub transform: OutputTransform,
+    pub enabled: bool,
+}
+impl OutputSettings {
+    fn parse(line: &str) -> Option<Self> {
+        let mut position = None;
+        let mut scale = 1.0;
+        let mut transform = OutputTransform::default();
+        let mut enabled = true;
+        for entry in line.split_whitespace() {
+            let (key, value) = entry.split_once(':')?;
+            match key {
+                "position" => {
+                    let (x, y) = value.split_once(',')?;
+                    position = Some(pal::Position2D::from((
+                        x.parse::<i32>().ok()?,
+                        y.parse::<i32>().ok()?,
+                    )));
+                }
+                "scale" => scale = value.parse().ok()?,
+                "transform" => transform = parse_transform(value)?,
+                "enabled" => enabled = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        if !scale.is_finite() || scale <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            position: position?,
+            scale,
+            transform,
+            enabled,
+        })
+    }
+
+    fn serialize(&self) -> String {
+        format!(
+            "position:{},{} scale:{} transform:{} enabled:{}",
+            self.position.x,
+            self.position.y,
+            self.scale,
+            transform_name(&self.transform),
+            self.enabled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Layout of a set of outputs, keyed by output name.
+pub struct OutputProfile {
+    pub outputs: BTreeMap<String, OutputSettings>,
+}
+impl OutputProfile {
+    /// Check if the profile describes exactly the given set of outputs.
+    pub fn matches(&self, names: &BTreeSet<String>) -> bool {
+        self.outputs.len() == names.len()
+            && names.iter().all(|name| self.outputs.contains_key(name))
+    }
+}
+
+#[derive(Debug)]
+/// Collection of output profiles, persisted in a config file.
+pub struct OutputProfiles {
+    path: Option<PathBuf>,
+    profiles: Vec<OutputProfile>,
+}
+impl OutputProfiles {
+    /// Load the profiles from `path`, a missing file is considered empty.
+    /// Without a path the profiles are only kept in memory.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let profiles = path
+            .as_ref()
+            .map(|path| match std::fs::read_to_string(path) {
+                Ok(content) => Self::parse(&content),
+                Err(error) => {
+                    if error.kind() != std::io::ErrorKind::NotFound {
+                        log::error!(target: "Output Profiles","Failed to read {:?}: {}",path,error);
+                    }
+                    Vec::new()
+                }
+            })
+            .unwrap_or(Vec::new());
+        Self { path, profiles }
+    }
+
+    /// Find the profile describing exactly the given set of outputs.
+    pub fn find(&self, names: &BTreeSet<String>) -> Option<&OutputProfile> {
+        self.profiles.iter().find(|profile| profile.matches(names))
+    }
+
+    /// Store a profile, replacing the one of the same set of outputs, and save the config file.
+    pub fn store(&mut self, profile: OutputProfile) {
+        let names = profile.outputs.keys().cloned().collect::<BTreeSet<_>>();
+        match self
+            .profiles
+            .iter_mut()
+            .find(|stored| stored.matches(&names))
+        {
+            Some(stored) if *stored == profile => return,
+            Some(stored) => *stored = profile,
+            None => self.profiles.push(profile),
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let result = path
+            .parent()
+            .map(|parent| std::fs::create_dir_all(parent))
+            .unwrap_or(Ok(()))
+            .and_then(|_| std::fs::write(path, self.serialize()));
+        if let Err(error) = result {
+            log::error!(target: "Output Profiles","Failed to write {:?}: {}",path,error);
+        }
+    }
+
+    fn parse(content: &str) -> Vec<OutputProfile> {
+        let mut profiles = Vec::new();
+        let mut current: Option<OutputProfile> = None;
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[profile]" {
+                profiles.extend(current.take());
+                current = Some(OutputProfile {
+                    outputs: BTreeMap::new(),
+                });
+                continue;
+            }
+            let parsed = line.split_once('=').map(|(name, settings)| {
+                OutputSettings::parse(settings).map(|settings| (name.trim().to_string(), settings))
+            });
+            match (current.as_mut(), parsed.flatten()) {
+                (Some(profile), Some((name, settings))) => {
+                    profile.outputs.insert(name, settings);
+                }
+                _ => {
+                    log::error!(target: "Output Profiles","Invalid line {} ignored: {}",index + 1,line)
+                }
+            }
+        }
+        profiles.extend(current);
+        profiles
+            .into_iter()
+            .filter(|profile| !profile.outputs.is_empty())
+            .collect()
+    }
+
+    fn serialize(&self) -> String {
+        self.profiles
+            .iter()
+            .map(|profile| {
+                std::iter::once(String::from("[profile]"))
+                    .chain(
+                        profile
+                            .outputs
+                            .iter()
+                            .map(|(name, settings)| format!("{} = {}", name, settings.serialize())),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            + "\n"
+    }
+}
+
+fn transform_name(transform: &OutputTransform) -> &'static str {
+    match transform {
+        OutputTransform::Normal => "normal",
+        OutputTransform::Rotated90 => "90",
+        OutputTransform::Rotated180 => "180",
+        OutputTransform::Rotated270 => "270",
+        OutputTransform::Flipped => "flipped",
+        OutputTransform::Flipped90 => "flipped-90",
+        OutputTransform::Flipped180 => "flipped-180",
+        OutputTransform::Flipped270 => "flipped-270",
+    }
+}
+
+fn parse_transform(name: &str) -> Option<OutputTransform> {
+    match name {
+        "normal" => Some(OutputTransform::Normal),
+        "90" => Some(OutputTransform::Rotated90),
+        "180" => Some(OutputTransform::Rotated180),
+        "270" => Some(OutputTransform::Rotated270),
+        "flipped" => Some(OutputTransform::Flipped),
+        "flipped-90" => Some(OutputTransform::Flipped90),
+        "flipped-180" => Some(OutputTransform::Flipped180),
+        "flipped-270" => Some(OutputTransform::Flipped270),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "
+[profile]
+eDP-1 = position:0,0 scale:2 transform:normal enabled:true
+HDMI-A-1 = position:1920,0 scale:1 transform:90 enabled:false
+
+# Laptop alone
+[profile]
+eDP-1 = position:0,0 scale:1.5 transform:flipped-270 enabled:true
+";
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_profiles() {
+        let profiles = OutputProfiles::parse(CONTENT);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(
+            profiles[0].outputs.get("HDMI-A-1"),
+            Some(&OutputSettings {
+                position: pal::Position2D::from((1920, 0)),
+                scale: 1.0,
+                transform: OutputTransform::Rotated90,
+                enabled: false,
+            })
+        );
+        assert_eq!(
+            profiles[1].outputs.get("eDP-1"),
+            Some(&OutputSettings {
+                position: pal::Position2D::from((0, 0)),
+                scale: 1.5,
+                transform: OutputTransform::Flipped270,
+                enabled: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_ignores_invalid_lines() {
+        let content = "
+eDP-1 = position:0,0
+[profile]
+eDP-1 = scale:2
+HDMI-A-1 = position:0,0 scale:0
+DP-1 = position:0,0 rotation:90
+DP-2 = position:10,20
+[profile]
+";
+        let profiles = OutputProfiles::parse(content);
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].outputs.len(), 1);
+        assert_eq!(
+            profiles[0].outputs.get("DP-2"),
+            Some(&OutputSettings {
+                position: pal::Position2D::from((10, 20)),
+                scale: 1.0,
+                transform: OutputTransform::default(),
+                enabled: true,
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let profiles = OutputProfiles {
+            path: None,
+            profiles: OutputProfiles::parse(CONTENT),
+        };
+        assert_eq!(
+            OutputProfiles::parse(&profiles.serialize()),
+            profiles.profiles
+        );
+    }
+
+    #[test]
+    fn find_matches_the_exact_set_of_outputs() {
+        let profiles = OutputProfiles {
+            path: None,
+            profiles: OutputProfiles::parse(CONTENT),
+        };
+        let both = profiles.find(&names(&["HDMI-A-1", "eDP-1"]));
+        assert_eq!(both.map(|profile| profile.outputs.len()), Some(2));
+        let laptop = profiles.find(&names(&["eDP-1"]));
+        assert_eq!(
+            laptop.map(|profile| profile.outputs["eDP-1"].scale),
+            Some(1.5)
+        );
+        assert!(profiles.find(&names(&["DP-1"])).is_none());
+    }
+
+    #[test]
+    fn store_replaces_the_profile_of_the_same_outputs() {
+        let mut profiles = OutputProfiles::load(None);
+        let mut profile = OutputProfiles::parse(CONTENT).remove(1);
+        profiles.store(profile.clone());
+        profile.outputs.get_mut("eDP-1").unwrap().scale = 1.0;
+        profiles.store(profile.clone());
+        assert_eq!(profiles.profiles, vec![profile]);
+    }
+}
@@ -1,12 +1,89 @@
 //! [SeatManager][SeatManager] related structures and enumerations.
 
+use super::{KeyboardConfig, KeyboardSettings, Keymap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Icons the compositor can show as cursor image.
+pub enum CursorIcon {
+    Default,
+    Move,
+    ResizeTop,
+    ResizeBottom,
+    ResizeLeft,
+    ResizeRight,
+    ResizeTopLeft,
+    ResizeTopRight,
+    ResizeBottomLeft,
+    ResizeBottomRight,
+}
+impl CursorIcon {
+    /// Xcursor names of the icon, in order of preference.
+    pub fn names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Default => &["left_ptr", "default", "top_left_arrow", "left-arrow"],
+            Self::Move => &["fleur", "move", "grabbing", "all-scroll"],
+            Self::ResizeTop => &["top_side", "n-resize", "ns-resize"],
+            Self::ResizeBottom => &["bottom_side", "s-resize", "ns-resize"],
+            Self::ResizeLeft => &["left_side", "w-resize", "ew-resize"],
+            Self::ResizeRight => &["right_side", "e-resize", "ew-resize"],
+            Self::ResizeTopLeft => &["top_left_corner", "nw-resize", "nwse-resize"],
+            Self::ResizeTopRight => &["top_right_corner", "ne-resize", "nesw-resize"],
+            Self::ResizeBottomLeft => &["bottom_left_corner", "sw-resize", "nesw-resize"],
+            Self::ResizeBottomRight => &["bottom_right_corner", "se-resize", "nwse-resize"],
+        }
+    }
+}
+impl Default for CursorIcon {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+impl From<ews::ResizeEdge> for CursorIcon {
+    fn from(edge: ews::ResizeEdge) -> Self {
+        match edge {
+            ews::ResizeEdge::Top => Self::ResizeTop,
+            ews::ResizeEdge::Bottom => Self::ResizeBottom,
+            ews::ResizeEdge::Left => Self::ResizeLeft,
+            ews::ResizeEdge::Right => Self::ResizeRight,
+            ews::ResizeEdge::TopLeft => Self::ResizeTopLeft,
+            ews::ResizeEdge::TopRight => Self::ResizeTopRight,
+            ews::ResizeEdge::BottomLeft => Self::ResizeBottomLeft,
+            ews::ResizeEdge::BottomRight => Self::ResizeBottomRight,
+            _ => Self::Default,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Image shown by a cursor.
+pub enum CursorImage {
+    /// Icon of the compositor cursor theme.
+    Icon(CursorIcon),
+    /// Surface provided by a client, drawn with its hotspot at the cursor position.
+    Surface {
+        handle: ews::WlSurface,
+        hotspot: pal::Offset2D<i32>,
+    },
+    Hidden,
+}
+impl Default for CursorImage {
+    fn default() -> Self {
+        Self::Icon(CursorIcon::Default)
+    }
+}
+impl From<CursorIcon> for CursorImage {
+    fn from(icon: CursorIcon) -> Self {
+        Self::Icon(icon)
+    }
+}
+
 #[derive(Debug, Clone)]
 /// Enumerator containing all the possible cursor requests.
 pub enum CursorRequest {
     Added {
         id: usize,
         position: pal::Position2D<i32>,
-        image: Option<usize>,
+        image: CursorImage,
     },
     Removed {
         id: usize,
@@ -15,6 +92,14 @@ pub enum CursorRequest {
         id: usize,
         position: pal::Position2D<i32>,
     },
+    Image {
+        id: usize,
+        image: CursorImage,
+    },
+    SurfaceCommitted {
+        handle: ews::WlSurface,
+        hotspot: pal::Offset2D<i32>,
+    },
     Button {
         id: usize,
         time: u32,
@@ -41,6 +126,16 @@ pub enum CursorRequest {
         id: usize,
         output_id: usize,
     },
+    Constrain {
+        id: usize,
+        surface: usize,
+        kind: ConstraintKind,
+        region: Option<Vec<pal::Rectangle<i32, u32>>>,
+    },
+    Unconstrain {
+        id: usize,
+        surface: usize,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -48,9 +143,16 @@ pub enum CursorRequest {
 pub enum KeyboardRequest {
     Added {
         id: usize,
+    },
+    RepeatInfo {
+        id: usize,
         rate: i32,
         delay: i32,
     },
+    Layout {
+        id: usize,
+        layout: String,
+    },
     Removed {
         id: usize,
     },
@@ -64,13 +166,36 @@ pub enum KeyboardRequest {
     Focus {
         id: Option<usize>,
     },
+    FocusNext {
+        id: usize,
+    },
+    FocusPrevious {
+        id: usize,
+    },
 }
 
 #[derive(Debug, Clone)]
 /// Enumerator containing all the possible seat requests.
 pub enum SeatRequest {
-    Added { id: usize, name: String },
-    Removed { id: usize },
+    Added {
+        id: usize,
+        name: String,
+    },
+    Removed {
+        id: usize,
+    },
+    PopupGrabStart {
+        id: usize,
+        serial: u32,
+        surface: usize,
+        parent: Option<usize>,
+    },
+    PopupGrabStop {
+        id: usize,
+    },
+    SwitchOutput {
+        id: usize,
+    },
     Cursor(CursorRequest),
     Keyboard(KeyboardRequest),
 }
@@ -86,7 +211,7 @@ pub enum CursorEvent {
     Added {
         id: usize,
         position: pal::Position2D<i32>,
-        image: Option<usize>,
+        image: CursorImage,
     },
     Removed {
         id: usize,
@@ -95,6 +220,10 @@ pub enum CursorEvent {
         id: usize,
         position: pal::Position2D<i32>,
     },
+    Image {
+        id: usize,
+        image: CursorImage,
+    },
     Button {
         id: usize,
         time: u32,
@@ -121,6 +250,15 @@ pub enum CursorEvent {
         id: usize,
         output_id: usize,
     },
+    ConstraintActivated {
+        id: usize,
+        surface: usize,
+        kind: ConstraintKind,
+    },
+    ConstraintDeactivated {
+        id: usize,
+        surface: usize,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -130,6 +268,16 @@ pub enum KeyboardEvent {
         id: usize,
         rate: i32,
         delay: i32,
+        keymap: Keymap,
+    },
+    RepeatInfo {
+        id: usize,
+        rate: i32,
+        delay: i32,
+    },
+    Keymap {
+        id: usize,
+        keymap: Keymap,
     },
     Removed {
         id: usize,
@@ -150,8 +298,23 @@ pub enum KeyboardEvent {
 #[derive(Debug, Clone)]
 /// Enumerator containing all the possible seat events.
 pub enum SeatEvent {
-    Added { id: usize, name: String },
-    Removed { id: usize },
+    Added {
+        id: usize,
+        name: String,
+    },
+    Removed {
+        id: usize,
+    },
+    PopupGrabStarted {
+        id: usize,
+        surface: usize,
+    },
+    PopupGrabStopped {
+        id: usize,
+        serial: u32,
+        surfaces: Vec<usize>,
+        focus: Option<usize>,
+    },
     Cursor(CursorEvent),
     Keyboard(KeyboardEvent),
 }
@@ -166,8 +329,9 @@ impl From<CursorEvent> for SeatEvent {
 pub struct Cursor {
     position: pal::Position2D<i32>,
     focus: Option<usize>,
-    image: Option<usize>,
+    image: CursorImage,
     output: Option<usize>,
+    constraints: Vec<PointerConstraint>,
 }
 impl Cursor {
     pub fn position(&self) -> &pal::Position2D<i32> {
@@ -176,6 +340,19 @@ impl Cursor {
     pub fn focus(&self) -> &Option<usize> {
         &self.focus
     }
+    pub fn image(&self) -> &CursorImage {
+        &self.image
+    }
+    /// Get the constraint requested by a surface.
+    pub fn constraint(&self, surface: usize) -> Option<&PointerConstraint> {
+        self.constraints
+            .iter()
+            .find(|constraint| constraint.surface == surface)
+    }
+    /// Get the constraint currently limiting the cursor motion.
+    pub fn active_constraint(&self) -> Option<&PointerConstraint> {
+        self.constraints.iter().find(|constraint| constraint.active)
+    }
     pub fn output(&self) -> &Option<usize> {
         &self.output
     }
@@ -187,6 +364,80 @@ pub struct Keyboard {
     focus: Option<usize>,
     rate: i32,
     delay: i32,
+    keymap: Keymap,
+}
+impl Keyboard {
+    /// Repeat rate in characters per second, used when neither the configuration nor the platform provide one.
+    pub const DEFAULT_RATE: i32 = 25;
+    /// Repeat delay in milliseconds, used when neither the configuration nor the platform provide one.
+    pub const DEFAULT_DELAY: i32 = 600;
+
+    pub fn focus(&self) -> &Option<usize> {
+        &self.focus
+    }
+    pub fn rate(&self) -> i32 {
+        self.rate
+    }
+    pub fn delay(&self) -> i32 {
+        self.delay
+    }
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Kinds of pointer constraint a client can request.
+pub enum ConstraintKind {
+    /// The cursor does not move at all.
+    Lock,
+    /// The cursor cannot leave the constraint region.
+    Confine,
+}
+
+#[derive(Debug, Clone)]
+/// Pointer constraint requested by a surface, that applies while the surface has the cursor focus.
+pub struct PointerConstraint {
+    surface: usize,
+    kind: ConstraintKind,
+    region: Option<Vec<pal::Rectangle<i32, u32>>>,
+    active: bool,
+}
+impl PointerConstraint {
+    pub fn surface(&self) -> usize {
+        self.surface
+    }
+    pub fn kind(&self) -> ConstraintKind {
+        self.kind
+    }
+    /// Rectangles of the region, relative to the surface. `None` covers the whole surface.
+    pub fn region(&self) -> Option<&Vec<pal::Rectangle<i32, u32>>> {
+        self.region.as_ref()
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Representation of a chain of popups grabbing the input of a seat.
+pub struct PopupGrab {
+    serial: u32,
+    root: usize,
+    popups: Vec<usize>,
+}
+impl PopupGrab {
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+    /// Surface that owns the popup chain.
+    pub fn root(&self) -> usize {
+        self.root
+    }
+    /// Grabbing popups, from the bottom one to the topmost one.
+    pub fn popups(&self) -> &[usize] {
+        &self.popups
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -196,18 +447,23 @@ pub struct Seat {
     name: String,
     cursor: Option<Cursor>,
     keyboard: Option<Keyboard>,
+    popup_grab: Option<PopupGrab>,
 }
 
 #[derive(Debug)]
 /// Component responsible to handle the seats.
 pub struct SeatManager {
     seats: Vec<Seat>,
+    keyboard_config: KeyboardConfig,
 }
 
 impl SeatManager {
-    pub fn new() -> Self {
+    pub fn new(keyboard_config: KeyboardConfig) -> Self {
         let seats = Vec::new();
-        Self { seats }
+        Self {
+            seats,
+            keyboard_config,
+        }
     }
     pub fn seat_ref(&self, id: usize) -> Option<&Seat> {
         let seat = self
@@ -233,11 +489,13 @@ impl SeatManager {
     pub fn add_seat(&mut self, id: usize, name: String) -> impl Iterator<Item = SeatEvent> + Clone {
         let cursor = None;
         let keyboard = None;
+        let popup_grab = None;
         let seat = Seat {
             id,
             name: name.clone(),
             cursor,
             keyboard,
+            popup_grab,
         };
         self.seats.push(seat);
         log::info!(target:"WComp","Seat manager | Seat added");
@@ -253,6 +511,128 @@ impl SeatManager {
         }
     }
 
+    pub fn popup_grab_ref(&self, id: usize) -> Option<&PopupGrab> {
+        self.seat_ref(id)
+            .map(|seat| seat.popup_grab.as_ref())
+            .flatten()
+    }
+
+    /// Get the grabbing popups that are kept if a new grab is started on a child of `parent`.
+    pub fn popup_grab_chain(&self, id: usize, parent: usize) -> Vec<usize> {
+        self.popup_grab_ref(id)
+            .map(|popup_grab| {
+                popup_grab
+                    .popups
+                    .iter()
+                    .position(|popup| *popup == parent)
+                    .map(|position| popup_grab.popups[..=position].to_vec())
+            })
+            .flatten()
+            .unwrap_or(Vec::new())
+    }
+
+    pub fn popup_grab_start(
+        &mut self,
+        id: usize,
+        serial: u32,
+        surface: usize,
+        parent: Option<usize>,
+        root: usize,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        let kept = parent
+            .map(|parent| self.popup_grab_chain(id, parent))
+            .unwrap_or(Vec::new());
+        self.seat_mut(id)
+            .map(|seat| {
+                //Popups of the old chain that are not parents of the new one are dismissed.
+                let dismissed = seat
+                    .popup_grab
+                    .take()
+                    .map(|popup_grab| {
+                        let surfaces = popup_grab
+                            .popups
+                            .into_iter()
+                            .skip(kept.len())
+                            .rev()
+                            .collect::<Vec<_>>();
+                        let focus = kept.last().cloned().or(Some(root));
+                        if surfaces.is_empty() {
+                            None
+                        } else {
+                            Some(SeatEvent::PopupGrabStopped {
+                                id,
+                                serial: popup_grab.serial,
+                                surfaces,
+                                focus,
+                            })
+                        }
+                    })
+                    .flatten();
+
+                let mut popups = kept;
+                popups.push(surface);
+                seat.popup_grab = Some(PopupGrab {
+                    serial,
+                    root,
+                    popups,
+                });
+                dismissed
+                    .into_iter()
+                    .chain(std::iter::once(SeatEvent::PopupGrabStarted { id, surface }))
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn popup_grab_stop(&mut self, id: usize) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.seat_mut(id)
+            .map(|seat| seat.popup_grab.take())
+            .flatten()
+            .map(|popup_grab| SeatEvent::PopupGrabStopped {
+                id,
+                serial: popup_grab.serial,
+                surfaces: popup_grab.popups.into_iter().rev().collect(),
+                focus: Some(popup_grab.root),
+            })
+            .into_iter()
+    }
+
+    /// Remove a destroyed surface from every popup grab,
+    /// dismissing the popups above it in the chain.
+    pub fn popup_grab_remove(&mut self, surface: usize) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.seats
+            .iter_mut()
+            .filter_map(|seat| {
+                let id = seat.id;
+                let position = seat
+                    .popup_grab
+                    .as_ref()
+                    .map(|popup_grab| popup_grab.popups.iter().position(|popup| *popup == surface))
+                    .flatten()?;
+                let popup_grab = seat.popup_grab.as_mut()?;
+                let surfaces = popup_grab
+                    .popups
+                    .split_off(position)
+                    .into_iter()
+                    .rev()
+                    .collect::<Vec<_>>();
+                let serial = popup_grab.serial;
+                let focus = popup_grab.popups.last().cloned().or(Some(popup_grab.root));
+                if popup_grab.popups.is_empty() {
+                    seat.popup_grab = None;
+                }
+                Some(SeatEvent::PopupGrabStopped {
+                    id,
+                    serial,
+                    surfaces,
+                    focus,
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     pub fn keyboard_ref(&self, id: usize) -> Option<&Keyboard> {
         self.seat_ref(id)
             .map(|seat| seat.keyboard.as_ref())
@@ -264,20 +644,25 @@ impl SeatManager {
             .flatten()
     }
 
-    pub fn add_keyboard(
-        &mut self,
-        id: usize,
-        rate: i32,
-        delay: i32,
-    ) -> impl Iterator<Item = SeatEvent> + Clone {
+    pub fn add_keyboard(&mut self, id: usize) -> impl Iterator<Item = SeatEvent> + Clone {
+        let settings = self.keyboard_settings(id);
         self.seat_mut(id)
             .map(|seat| {
                 let focus = None;
-                seat.keyboard = Some(Keyboard { focus, rate, delay });
+                let rate = settings.rate.unwrap_or(Keyboard::DEFAULT_RATE);
+                let delay = settings.delay.unwrap_or(Keyboard::DEFAULT_DELAY);
+                let keymap = settings.keymap();
+                seat.keyboard = Some(Keyboard {
+                    focus,
+                    rate,
+                    delay,
+                    keymap: keymap.clone(),
+                });
                 vec![SeatEvent::Keyboard(KeyboardEvent::Added {
                     id,
                     rate,
                     delay,
+                    keymap,
                 })]
                 .into_iter()
             })
@@ -285,6 +670,60 @@ impl SeatManager {
             .flatten()
     }
 
+    /// Change the repeat rate and delay reported by the platform,
+    /// unless they are set in the configuration.
+    pub fn keyboard_repeat_info(
+        &mut self,
+        id: usize,
+        rate: i32,
+        delay: i32,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        if self.keyboard_settings(id).has_repeat() {
+            return Vec::new().into_iter();
+        }
+        self.keyboard_mut(id)
+            .filter(|keyboard| keyboard.rate != rate || keyboard.delay != delay)
+            .map(|keyboard| {
+                keyboard.rate = rate;
+                keyboard.delay = delay;
+                vec![SeatEvent::Keyboard(KeyboardEvent::RepeatInfo {
+                    id,
+                    rate,
+                    delay,
+                })]
+            })
+            .unwrap_or(Vec::new())
+            .into_iter()
+    }
+
+    /// Change the layout reported by the platform, unless the keymap is set in the configuration.
+    pub fn keyboard_layout(
+        &mut self,
+        id: usize,
+        layout: String,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        if self.keyboard_settings(id).has_keymap() {
+            return Vec::new().into_iter();
+        }
+        self.keyboard_mut(id)
+            .filter(|keyboard| keyboard.keymap.layout != layout)
+            .map(|keyboard| {
+                //The variant belongs to the previous layout.
+                keyboard.keymap.layout = layout;
+                keyboard.keymap.variant = String::new();
+                let keymap = keyboard.keymap.clone();
+                vec![SeatEvent::Keyboard(KeyboardEvent::Keymap { id, keymap })]
+            })
+            .unwrap_or(Vec::new())
+            .into_iter()
+    }
+
+    fn keyboard_settings(&self, id: usize) -> KeyboardSettings {
+        self.seat_ref(id)
+            .map(|seat| self.keyboard_config.settings(&seat.name))
+            .unwrap_or_default()
+    }
+
     pub fn del_keyboard(&mut self, id: usize) -> impl Iterator<Item = SeatEvent> + Clone {
         self.seat_mut(id)
             .map(|seat| {
@@ -309,6 +748,31 @@ impl SeatManager {
             .flatten()
     }
 
+    /// Remove the keyboard focus from the specified surface on every seat.
+    pub fn keyboard_unfocus(&mut self, surface: usize) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.seats
+            .iter_mut()
+            .filter_map(|seat| {
+                let id = seat.id;
+                seat.keyboard
+                    .as_mut()
+                    .map(|keyboard| {
+                        if keyboard.focus == Some(surface) {
+                            keyboard.focus = None;
+                            Some(SeatEvent::Keyboard(KeyboardEvent::Focus {
+                                id,
+                                surface: None,
+                            }))
+                        } else {
+                            None
+                        }
+                    })
+                    .flatten()
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     pub fn keyboard_key(
         &mut self,
         id: usize,
@@ -335,6 +799,16 @@ impl SeatManager {
     pub fn cursor_ref(&self, id: usize) -> Option<&Cursor> {
         self.seat_ref(id).map(|seat| seat.cursor.as_ref()).flatten()
     }
+    pub fn cursors_ref(&self) -> impl Iterator<Item = &Cursor> {
+        self.seats.iter().filter_map(|seat| seat.cursor.as_ref())
+    }
+    /// Get the ids of the seats having a cursor.
+    pub fn cursor_seats(&self) -> impl Iterator<Item = usize> + '_ {
+        self.seats
+            .iter()
+            .filter(|seat| seat.cursor.is_some())
+            .map(|seat| seat.id)
+    }
     fn cursor_mut(&mut self, id: usize) -> Option<&mut Cursor> {
         self.seat_mut(id).map(|seat| seat.cursor.as_mut()).flatten()
     }
@@ -343,17 +817,19 @@ impl SeatManager {
         &mut self,
         id: usize,
         position: pal::Position2D<i32>,
-        image: Option<usize>,
+        image: CursorImage,
     ) -> impl Iterator<Item = SeatEvent> + Clone {
         self.seat_mut(id)
             .map(|seat| {
                 let output = None;
                 let focus = None;
+                let constraints = Vec::new();
                 seat.cursor = Some(Cursor {
                     focus,
                     position: position.clone(),
                     image,
                     output,
+                    constraints,
                 });
                 log::info!(target:"WComp","Seat manager | Cursor added");
                 SeatEvent::from(CursorEvent::Added {
@@ -415,6 +891,52 @@ impl SeatManager {
         .into_iter()
     }
 
+    pub fn set_cursor_image(
+        &mut self,
+        id: usize,
+        image: CursorImage,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.seat_mut(id)
+            .map(|seat| seat.cursor.as_mut())
+            .flatten()
+            .map(|cursor| {
+                cursor.image = image.clone();
+                SeatEvent::from(CursorEvent::Image { id, image })
+            })
+            .into_iter()
+    }
+
+    /// Update the hotspot of the cursors showing a client surface, that has new content to draw.
+    pub fn commit_cursor_surface(
+        &mut self,
+        handle: ews::WlSurface,
+        hotspot: pal::Offset2D<i32>,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.seats
+            .iter_mut()
+            .filter_map(|seat| {
+                let id = seat.id;
+                seat.cursor
+                    .as_mut()
+                    .filter(|cursor| match &cursor.image {
+                        CursorImage::Surface {
+                            handle: surface, ..
+                        } => *surface == handle,
+                        _ => false,
+                    })
+                    .map(|cursor| {
+                        let image = CursorImage::Surface {
+                            handle: handle.clone(),
+                            hotspot: hotspot.clone(),
+                        };
+                        cursor.image = image.clone();
+                        SeatEvent::from(CursorEvent::Image { id, image })
+                    })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     pub fn move_cursor(
         &mut self,
         id: usize,
@@ -430,6 +952,110 @@ impl SeatManager {
             .flatten()
             .into_iter()
     }
+    /// Add the constraint requested by a surface, replacing its previous one.
+    pub fn constrain_cursor(
+        &mut self,
+        id: usize,
+        surface: usize,
+        kind: ConstraintKind,
+        region: Option<Vec<pal::Rectangle<i32, u32>>>,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        let mut events = self.unconstrain_cursor(id, surface).collect::<Vec<_>>();
+        if let Some(cursor) = self.cursor_mut(id) {
+            let active = false;
+            cursor.constraints.push(PointerConstraint {
+                surface,
+                kind,
+                region,
+                active,
+            });
+        } else {
+            events.clear();
+        }
+        events.into_iter()
+    }
+
+    /// Remove the constraint requested by a surface, deactivating it if needed.
+    pub fn unconstrain_cursor(
+        &mut self,
+        id: usize,
+        surface: usize,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.cursor_mut(id)
+            .map(|cursor| {
+                let position = cursor
+                    .constraints
+                    .iter()
+                    .position(|constraint| constraint.surface == surface)?;
+                let constraint = cursor.constraints.remove(position);
+                Some(SeatEvent::from(CursorEvent::ConstraintDeactivated {
+                    id,
+                    surface,
+                }))
+                .filter(|_| constraint.active)
+            })
+            .flatten()
+            .into_iter()
+    }
+
+    /// Remove the constraints requested by a destroyed surface on every seat.
+    pub fn constraints_remove(
+        &mut self,
+        surface: usize,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        let ids = self.cursor_seats().collect::<Vec<_>>();
+        ids.into_iter()
+            .map(|id| self.unconstrain_cursor(id, surface).collect::<Vec<_>>())
+            .flatten()
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Activate the constraint of a surface, deactivating the previously active one.
+    pub fn activate_constraint(
+        &mut self,
+        id: usize,
+        surface: usize,
+    ) -> impl Iterator<Item = SeatEvent> + Clone {
+        let mut events = self.deactivate_constraint(id).collect::<Vec<_>>();
+        let activated = self
+            .cursor_mut(id)
+            .map(|cursor| {
+                cursor
+                    .constraints
+                    .iter_mut()
+                    .find(|constraint| constraint.surface == surface)
+            })
+            .flatten()
+            .map(|constraint| {
+                constraint.active = true;
+                let kind = constraint.kind;
+                SeatEvent::from(CursorEvent::ConstraintActivated { id, surface, kind })
+            });
+        events.extend(activated);
+        events.into_iter()
+    }
+
+    /// Deactivate the active constraint of a cursor, if any.
+    pub fn deactivate_constraint(&mut self, id: usize) -> impl Iterator<Item = SeatEvent> + Clone {
+        self.cursor_mut(id)
+            .map(|cursor| {
+                cursor
+                    .constraints
+                    .iter_mut()
+                    .find(|constraint| constraint.active)
+            })
+            .flatten()
+            .map(|constraint| {
+                constraint.active = false;
+                let surface = constraint.surface;
+                SeatEvent::from(CursorEvent::ConstraintDeactivated { id, surface })
+            })
+            .into_iter()
+    }
+
+    /// Change the surface focused by a cursor.
+    /// Images set by the previously focused client are reset, clients set their own on enter.
     pub fn focus_cursor(
         &mut self,
         id: usize,
@@ -437,20 +1063,25 @@ impl SeatManager {
     ) -> impl Iterator<Item = SeatEvent> + Clone {
         self.seat_mut(id)
             .map(|seat| {
-                seat.cursor
-                    .as_mut()
-                    .map(|cursor| {
-                        if cursor.focus != surface {
-                            cursor.focus = surface;
-                            Some(SeatEvent::from(CursorEvent::Focus { id, surface }))
-                        } else {
-                            None
+                seat.cursor.as_mut().map(|cursor| {
+                    if cursor.focus != surface {
+                        cursor.focus = surface;
+                        let mut events = vec![SeatEvent::from(CursorEvent::Focus { id, surface })];
+                        //Icons are set by the compositor grabs, that reset them once ended.
+                        if !matches!(cursor.image, CursorImage::Icon(_)) {
+                            let image = CursorImage::default();
+                            cursor.image = image.clone();
+                            events.push(SeatEvent::from(CursorEvent::Image { id, image }));
                         }
-                    })
-                    .flatten()
+                        events
+                    } else {
+                        Vec::new()
+                    }
+                })
             })
             .flatten()
             .into_iter()
+            .flatten()
     }
     pub fn cursor_button(
         &mut self,
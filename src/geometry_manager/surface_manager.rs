@@ -18,6 +18,20 @@ pub enum SurfaceRequest {
         id: usize,
         position: pal::Position2D<i32>,
     },
+    InteractiveMoveStart {
+        id: usize,
+        serial: u32,
+    },
+    InteractiveMove {
+        id: usize,
+        serial: u32,
+        position: pal::Position2D<i32>,
+        cursor: pal::Position2D<i32>,
+    },
+    InteractiveMoveStop {
+        id: usize,
+        serial: u32,
+    },
     InteractiveResizeStart {
         id: usize,
         serial: u32,
@@ -47,6 +61,7 @@ pub enum SurfaceRequest {
         handle: ews::WlBuffer,
         inner_geometry: pal::Rectangle<i32, u32>,
         size: pal::Size2D<u32>,
+        scale: u32,
     },
     DetachBuffer {
         id: usize,
@@ -57,6 +72,19 @@ pub enum SurfaceRequest {
     Unmaximize {
         id: usize,
     },
+    Fullscreen {
+        id: usize,
+        output: Option<usize>,
+    },
+    Unfullscreen {
+        id: usize,
+    },
+    Minimize {
+        id: usize,
+    },
+    Restore {
+        id: usize,
+    },
     Commit {
         id: usize,
     },
@@ -77,6 +105,14 @@ pub enum SurfaceEvent {
         position: pal::Position2D<i32>,
         depth: u32,
     },
+    InteractiveMoveStarted {
+        id: usize,
+        serial: u32,
+    },
+    InteractiveMoveStopped {
+        id: usize,
+        serial: u32,
+    },
     InteractiveResizeStarted {
         id: usize,
         serial: u32,
@@ -133,11 +169,108 @@ pub enum SurfaceEvent {
     Unmaximized {
         id: usize,
     },
+    Tiled {
+        id: usize,
+        zone: TileZone,
+    },
+    Untiled {
+        id: usize,
+    },
+    TilingPreview {
+        id: usize,
+        geometry: Option<pal::Rectangle<i32, u32>>,
+    },
+    Fullscreened {
+        id: usize,
+        output: Option<usize>,
+    },
+    Unfullscreened {
+        id: usize,
+    },
+    Minimized {
+        id: usize,
+    },
+    Restored {
+        id: usize,
+    },
+    PopupConfiguration {
+        id: usize,
+        geometry: pal::Rectangle<i32, u32>,
+    },
+    OutputEntered {
+        id: usize,
+        output: usize,
+    },
+    OutputLeft {
+        id: usize,
+        output: usize,
+    },
     Committed {
         id: usize,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Areas of an output a surface can be tiled to by dragging it against the output edges.
+pub enum TileZone {
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Maximized,
+}
+impl TileZone {
+    /// Get the zone related to a cursor position within `threshold` from the edges of `output`.
+    pub fn from_position(
+        position: &pal::Position2D<i32>,
+        output: &pal::Rectangle<i32, u32>,
+        threshold: i32,
+    ) -> Option<Self> {
+        let left = position.x - output.position.x <= threshold;
+        let right = output.position.x + output.size.width as i32 - 1 - position.x <= threshold;
+        let top = position.y - output.position.y <= threshold;
+        let bottom = output.position.y + output.size.height as i32 - 1 - position.y <= threshold;
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(Self::TopLeft),
+            (_, true, true, _) => Some(Self::TopRight),
+            (true, _, _, true) => Some(Self::BottomLeft),
+            (_, true, _, true) => Some(Self::BottomRight),
+            (true, _, _, _) => Some(Self::Left),
+            (_, true, _, _) => Some(Self::Right),
+            (_, _, true, _) => Some(Self::Maximized),
+            _ => None,
+        }
+    }
+
+    /// Get the area covered by the zone in `output`.
+    pub fn geometry(&self, output: &pal::Rectangle<i32, u32>) -> pal::Rectangle<i32, u32> {
+        let half_width = output.size.width / 2;
+        let half_height = output.size.height / 2;
+        let (x, width) = match self {
+            Self::Left | Self::TopLeft | Self::BottomLeft => (output.position.x, half_width),
+            Self::Right | Self::TopRight | Self::BottomRight => (
+                output.position.x + half_width as i32,
+                output.size.width - half_width,
+            ),
+            Self::Maximized => (output.position.x, output.size.width),
+        };
+        let (y, height) = match self {
+            Self::TopLeft | Self::TopRight => (output.position.y, half_height),
+            Self::BottomLeft | Self::BottomRight => (
+                output.position.y + half_height as i32,
+                output.size.height - half_height,
+            ),
+            Self::Left | Self::Right | Self::Maximized => (output.position.y, output.size.height),
+        };
+        pal::Rectangle::from((
+            pal::Position2D::from((x, y)),
+            pal::Size2D::from((width, height)),
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 /// Representation of the possible altered states.
 pub struct AlteredState {
@@ -145,23 +278,30 @@ pub struct AlteredState {
     minimized: bool,
     maximized: bool,
     fullscreen: bool,
+    tiled: Option<TileZone>,
     resizing: Option<(u32, ews::ResizeEdge)>, //Serial
     moving: Option<u32>,                      //Serial
+    /// Offset of the cursor from the surface position, once untiling changed it during a move.
+    move_offset: Option<pal::Offset2D<i32>>,
 }
 impl AlteredState {
     pub fn new(original: pal::Rectangle<i32, u32>) -> Self {
         let minimized = false;
         let maximized = false;
         let fullscreen = false;
+        let tiled = None;
         let resizing = None;
         let moving = None;
+        let move_offset = None;
         Self {
             original,
             minimized,
             maximized,
             fullscreen,
+            tiled,
             resizing,
             moving,
+            move_offset,
         }
     }
 
@@ -174,6 +314,9 @@ impl AlteredState {
     pub fn is_fullscreen(&self) -> bool {
         self.fullscreen
     }
+    pub fn is_tiled(&self) -> bool {
+        self.tiled.is_some()
+    }
     pub fn is_resizing(&self) -> bool {
         self.resizing.is_some()
     }
@@ -190,11 +333,17 @@ impl AlteredState {
         !self.is_minimized()
             && !self.is_maximized()
             && !self.is_fullscreen()
+            && !self.is_tiled()
             && !self.is_resizing()
             && !self.is_moving()
     }
     pub fn start_interactive_resize(&mut self, serial: u32, edge: ews::ResizeEdge) -> bool {
-        if self.resizing.is_some() | self.moving.is_some() | self.maximized | self.minimized {
+        if self.resizing.is_some()
+            | self.moving.is_some()
+            | self.maximized
+            | self.minimized
+            | self.fullscreen
+        {
             false
         } else {
             self.resizing = Some((serial, edge));
@@ -215,6 +364,28 @@ impl AlteredState {
             None => true,
         }
     }
+    pub fn start_interactive_move(&mut self, serial: u32) -> bool {
+        if self.resizing.is_some()
+            | self.moving.is_some()
+            | self.maximized
+            | self.minimized
+            | self.fullscreen
+        {
+            false
+        } else {
+            self.moving = Some(serial);
+            true
+        }
+    }
+    pub fn stop_interactive_move(&mut self, serial: u32) -> bool {
+        if Some(serial) == self.moving {
+            self.moving = None;
+            self.move_offset = None;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -256,6 +427,31 @@ impl SurfaceState {
         result == Some(true)
     }
 
+    pub fn start_interactive_move(
+        &mut self,
+        serial: u32,
+        inner_geometry: pal::Rectangle<i32, u32>,
+    ) -> bool {
+        if self.altered_state.is_none() {
+            self.altered_state = Some(AlteredState::new(inner_geometry));
+        }
+        let result = self
+            .altered_state
+            .as_mut()
+            .map(|altered_state| altered_state.start_interactive_move(serial));
+        result == Some(true)
+    }
+    pub fn stop_interactive_move(&mut self, serial: u32) -> bool {
+        let result = self
+            .altered_state
+            .as_mut()
+            .map(|altered_state| altered_state.stop_interactive_move(serial));
+        if self.is_empty() {
+            self.altered_state = None;
+        }
+        result == Some(true)
+    }
+
     pub fn is_minimized(&self) -> bool {
         self.altered_state
             .as_ref()
@@ -274,6 +470,32 @@ impl SurfaceState {
             .map(|altered_state| altered_state.is_fullscreen())
             == Some(true)
     }
+    /// Get the offset of the cursor from the surface position, if untiling changed it during a move.
+    pub fn move_offset(&self) -> Option<&pal::Offset2D<i32>> {
+        self.altered_state
+            .as_ref()
+            .map(|altered_state| altered_state.move_offset.as_ref())
+            .flatten()
+    }
+    /// Get the geometry the surface is restored to once it leaves its altered states.
+    pub fn original(&self) -> Option<&pal::Rectangle<i32, u32>> {
+        self.altered_state
+            .as_ref()
+            .map(|altered_state| &altered_state.original)
+    }
+    pub fn is_tiled(&self) -> bool {
+        self.altered_state
+            .as_ref()
+            .map(|altered_state| altered_state.is_tiled())
+            == Some(true)
+    }
+    /// Get the zone the surface is tiled to.
+    pub fn tile_zone(&self) -> Option<TileZone> {
+        self.altered_state
+            .as_ref()
+            .map(|altered_state| altered_state.tiled)
+            .flatten()
+    }
     pub fn is_resizing(&self) -> bool {
         self.altered_state
             .as_ref()
@@ -315,6 +537,203 @@ pub struct PopupState {
     pub constraint_adjustment: ews::ConstraintAdjustment,
     pub offset: pal::Offset2D<i32>,
     pub reactive: bool,
+    /// Area the popup should be kept into, usually the output containing the parent.
+    pub bounds: Option<pal::Rectangle<i32, u32>>,
+    /// Last geometry sent to the client, relative to the parent window geometry.
+    pub geometry: Option<pal::Rectangle<i32, u32>>,
+}
+impl PopupState {
+    /// Compute the absolute position of a popup of the specified size
+    /// using the given anchor edges and gravity.
+    fn anchored_position(
+        &self,
+        parent_origin: &pal::Position2D<i32>,
+        size: &pal::Size2D<u32>,
+        anchor_edges: ews::Anchor,
+        gravity: ews::Gravity,
+        offset: &pal::Offset2D<i32>,
+    ) -> pal::Position2D<i32> {
+        let anchor_x = parent_origin.x + self.anchor.position.x;
+        let anchor_y = parent_origin.y + self.anchor.position.y;
+        let anchor_width = self.anchor.size.width as i32;
+        let anchor_height = self.anchor.size.height as i32;
+
+        let x = match anchor_edges {
+            ews::Anchor::Left | ews::Anchor::TopLeft | ews::Anchor::BottomLeft => anchor_x,
+            ews::Anchor::Right | ews::Anchor::TopRight | ews::Anchor::BottomRight => {
+                anchor_x + anchor_width
+            }
+            _ => anchor_x + anchor_width / 2,
+        };
+        let y = match anchor_edges {
+            ews::Anchor::Top | ews::Anchor::TopLeft | ews::Anchor::TopRight => anchor_y,
+            ews::Anchor::Bottom | ews::Anchor::BottomLeft | ews::Anchor::BottomRight => {
+                anchor_y + anchor_height
+            }
+            _ => anchor_y + anchor_height / 2,
+        };
+
+        let x = match gravity {
+            ews::Gravity::Left | ews::Gravity::TopLeft | ews::Gravity::BottomLeft => {
+                x - size.width as i32
+            }
+            ews::Gravity::Right | ews::Gravity::TopRight | ews::Gravity::BottomRight => x,
+            _ => x - size.width as i32 / 2,
+        };
+        let y = match gravity {
+            ews::Gravity::Top | ews::Gravity::TopLeft | ews::Gravity::TopRight => {
+                y - size.height as i32
+            }
+            ews::Gravity::Bottom | ews::Gravity::BottomLeft | ews::Gravity::BottomRight => y,
+            _ => y - size.height as i32 / 2,
+        };
+        pal::Position2D::from((x + offset.x, y + offset.y))
+    }
+
+    /// Compute the absolute geometry of a popup of the specified size,
+    /// adjusted following the positioner constraint rules to fit into the bounds.
+    pub fn constrained_geometry(
+        &self,
+        parent_origin: &pal::Position2D<i32>,
+        size: pal::Size2D<u32>,
+    ) -> pal::Rectangle<i32, u32> {
+        let position = self.anchored_position(
+            parent_origin,
+            &size,
+            self.anchor_edges,
+            self.gravity,
+            &self.offset,
+        );
+        let bounds = match &self.bounds {
+            Some(bounds) => bounds,
+            None => return pal::Rectangle::from((position, size)),
+        };
+        let adjustment = self.constraint_adjustment;
+
+        //Flipping an axis flips the offset on that axis as well.
+        let flipped_x = self
+            .anchored_position(
+                parent_origin,
+                &size,
+                Self::flip_anchor_x(self.anchor_edges),
+                Self::flip_gravity_x(self.gravity),
+                &pal::Offset2D::from((-self.offset.x, self.offset.y)),
+            )
+            .x;
+        let (x, width) = Self::constrain_axis(
+            (position.x, size.width),
+            flipped_x,
+            (bounds.position.x, bounds.size.width),
+            adjustment.contains(ews::ConstraintAdjustment::FlipX),
+            adjustment.contains(ews::ConstraintAdjustment::SlideX),
+            adjustment.contains(ews::ConstraintAdjustment::ResizeX),
+        );
+
+        let flipped_y = self
+            .anchored_position(
+                parent_origin,
+                &size,
+                Self::flip_anchor_y(self.anchor_edges),
+                Self::flip_gravity_y(self.gravity),
+                &pal::Offset2D::from((self.offset.x, -self.offset.y)),
+            )
+            .y;
+        let (y, height) = Self::constrain_axis(
+            (position.y, size.height),
+            flipped_y,
+            (bounds.position.y, bounds.size.height),
+            adjustment.contains(ews::ConstraintAdjustment::FlipY),
+            adjustment.contains(ews::ConstraintAdjustment::SlideY),
+            adjustment.contains(ews::ConstraintAdjustment::ResizeY),
+        );
+
+        pal::Rectangle::from((
+            pal::Position2D::from((x, y)),
+            pal::Size2D::from((width, height)),
+        ))
+    }
+
+    /// Apply flip, slide and resize adjustments, in this order, on a single axis.
+    fn constrain_axis(
+        (start, length): (i32, u32),
+        flipped_start: i32,
+        (bounds_start, bounds_length): (i32, u32),
+        flip: bool,
+        slide: bool,
+        resize: bool,
+    ) -> (i32, u32) {
+        let bounds_end = bounds_start + bounds_length as i32;
+        let fits =
+            |start: i32, length: u32| start >= bounds_start && start + length as i32 <= bounds_end;
+
+        let mut start = start;
+        let mut length = length;
+        if !fits(start, length) && flip && fits(flipped_start, length) {
+            start = flipped_start;
+        }
+        if !fits(start, length) && slide {
+            if start + length as i32 > bounds_end {
+                start = bounds_end - length as i32;
+            }
+            if start < bounds_start {
+                start = bounds_start;
+            }
+        }
+        if !fits(start, length) && resize {
+            let new_start = start.max(bounds_start);
+            let new_end = (start + length as i32).min(bounds_end);
+            if new_end > new_start {
+                start = new_start;
+                length = (new_end - new_start) as u32;
+            }
+        }
+        (start, length)
+    }
+
+    fn flip_anchor_x(anchor: ews::Anchor) -> ews::Anchor {
+        match anchor {
+            ews::Anchor::Left => ews::Anchor::Right,
+            ews::Anchor::Right => ews::Anchor::Left,
+            ews::Anchor::TopLeft => ews::Anchor::TopRight,
+            ews::Anchor::TopRight => ews::Anchor::TopLeft,
+            ews::Anchor::BottomLeft => ews::Anchor::BottomRight,
+            ews::Anchor::BottomRight => ews::Anchor::BottomLeft,
+            other => other,
+        }
+    }
+    fn flip_anchor_y(anchor: ews::Anchor) -> ews::Anchor {
+        match anchor {
+            ews::Anchor::Top => ews::Anchor::Bottom,
+            ews::Anchor::Bottom => ews::Anchor::Top,
+            ews::Anchor::TopLeft => ews::Anchor::BottomLeft,
+            ews::Anchor::BottomLeft => ews::Anchor::TopLeft,
+            ews::Anchor::TopRight => ews::Anchor::BottomRight,
+            ews::Anchor::BottomRight => ews::Anchor::TopRight,
+            other => other,
+        }
+    }
+    fn flip_gravity_x(gravity: ews::Gravity) -> ews::Gravity {
+        match gravity {
+            ews::Gravity::Left => ews::Gravity::Right,
+            ews::Gravity::Right => ews::Gravity::Left,
+            ews::Gravity::TopLeft => ews::Gravity::TopRight,
+            ews::Gravity::TopRight => ews::Gravity::TopLeft,
+            ews::Gravity::BottomLeft => ews::Gravity::BottomRight,
+            ews::Gravity::BottomRight => ews::Gravity::BottomLeft,
+            other => other,
+        }
+    }
+    fn flip_gravity_y(gravity: ews::Gravity) -> ews::Gravity {
+        match gravity {
+            ews::Gravity::Top => ews::Gravity::Bottom,
+            ews::Gravity::Bottom => ews::Gravity::Top,
+            ews::Gravity::TopLeft => ews::Gravity::BottomLeft,
+            ews::Gravity::BottomLeft => ews::Gravity::TopLeft,
+            ews::Gravity::TopRight => ews::Gravity::BottomRight,
+            ews::Gravity::BottomRight => ews::Gravity::TopRight,
+            other => other,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -342,6 +761,12 @@ impl SurfaceKind {
             Self::Popup { .. } => true,
         }
     }
+    pub fn check_move(&self, serial: u32) -> bool {
+        match self {
+            Self::Toplevel { state, .. } => state.is_moving_width(serial),
+            Self::Popup { .. } => false,
+        }
+    }
 }
 impl From<ews::ToplevelSurface> for SurfaceKind {
     fn from(handle: ews::ToplevelSurface) -> Self {
@@ -355,6 +780,7 @@ impl From<ews::ToplevelSurface> for SurfaceKind {
 pub struct Buffer {
     handle: ews::WlBuffer,
     geometry: pal::Rectangle<i32, u32>,
+    scale: u32,
     //pub size: pal::Size2D<u32>
 }
 impl Buffer {
@@ -364,7 +790,16 @@ impl Buffer {
     pub fn geometry(&self) -> &pal::Rectangle<i32, u32> {
         &self.geometry
     }
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+    /// Get the logical size of the buffer, that is its size in pixels divided by its scale.
     pub fn size(&self) -> pal::Size2D<u32> {
+        let size = self.pixel_size();
+        pal::Size2D::from((size.width / self.scale, size.height / self.scale))
+    }
+    /// Get the size of the buffer in pixels.
+    pub fn pixel_size(&self) -> pal::Size2D<u32> {
         use ews::Buffer;
         match ews::buffer_type(&self.handle) {
             Some(ews::BufferType::Shm) => ews::with_buffer_contents(&self.handle, |_data, info| {
@@ -393,6 +828,7 @@ pub struct Surface {
     max_size: pal::Size2D<u32>,
     position: pal::Position2D<i32>,
     depth: u32,
+    output: Option<usize>,
     children: VecDeque<Box<Surface>>,
 }
 impl Surface {
@@ -408,6 +844,10 @@ impl Surface {
     pub fn depth(&self) -> u32 {
         self.depth
     }
+    /// Get the output the surface is currently shown on.
+    pub fn output(&self) -> Option<usize> {
+        self.output
+    }
     pub fn size(&self) -> Option<pal::Size2D<u32>> {
         self.buffer.as_ref().map(|buffer| buffer.size())
     }
@@ -415,6 +855,41 @@ impl Surface {
         self.size()
             .map(|size| pal::Rectangle::from((self.position().clone(), size)))
     }
+    pub fn min_size(&self) -> &pal::Size2D<u32> {
+        &self.min_size
+    }
+    pub fn max_size(&self) -> &pal::Size2D<u32> {
+        &self.max_size
+    }
+    /// Clamp a size between the minimum and maximum size requested by the client
+    /// (where 0 means unconstrained), and the compositor `minimum`.
+    pub fn clamp_size(
+        &self,
+        size: pal::Size2D<u32>,
+        minimum: &pal::Size2D<u32>,
+    ) -> pal::Size2D<u32> {
+        pal::Size2D {
+            width: Self::clamp_length(
+                size.width,
+                self.min_size.width,
+                self.max_size.width,
+                minimum.width,
+            ),
+            height: Self::clamp_length(
+                size.height,
+                self.min_size.height,
+                self.max_size.height,
+                minimum.height,
+            ),
+        }
+    }
+    /// Clamp a length between the client limits, a null maximum meaning no limit,
+    /// the compositor `minimum` taking precedence over them.
+    fn clamp_length(value: u32, min: u32, max: u32, minimum: u32) -> u32 {
+        let value = value.max(min);
+        let value = if max > 0 { value.min(max) } else { value };
+        value.max(minimum)
+    }
     pub fn inner_geometry(&self) -> Option<&pal::Rectangle<i32, u32>> {
         self.buffer.as_ref().map(|buffer| buffer.geometry())
     }
@@ -424,6 +899,15 @@ impl Surface {
     pub fn buffer(&self) -> Option<&ews::WlBuffer> {
         self.buffer.as_ref().map(|buffer| &buffer.handle)
     }
+    pub fn state(&self) -> Option<&SurfaceState> {
+        match &self.kind {
+            SurfaceKind::Toplevel { state, .. } => Some(state),
+            SurfaceKind::Popup { .. } => None,
+        }
+    }
+    pub fn is_minimized(&self) -> bool {
+        self.state().map(|state| state.is_minimized()) == Some(true)
+    }
 
     pub fn add_child(&mut self, surface: Surface) -> impl Iterator<Item = SurfaceEvent> + Clone {
         let event = SurfaceEvent::Added {
@@ -434,22 +918,38 @@ impl Surface {
         std::iter::once(event) //.chain(self.update_children())
     }
     pub fn del_child(&mut self, id: usize) -> Option<impl Iterator<Item = SurfaceEvent> + Clone> {
-        self.children
-            .iter()
-            .position(|surface| surface.id == id)
-            .map(|position| {
-                self.children
-                    .remove(position)
-                    .map(|removed_surface| {
-                        removed_surface
-                            .buffer
-                            .map(|_| SurfaceEvent::BufferDetached { id })
-                            .into_iter()
-                            .chain(std::iter::once(SurfaceEvent::Removed { id }))
-                    })
+        //Children of a minimized surface are not present in the screen task.
+        let minimized = self.is_minimized();
+        self.del_descendant(id, minimized)
+            .map(|events| events.into_iter())
+    }
+    fn del_descendant(&mut self, id: usize, minimized: bool) -> Option<Vec<SurfaceEvent>> {
+        match self.children.iter().position(|surface| surface.id == id) {
+            Some(position) => self
+                .children
+                .remove(position)
+                .map(|removed_surface| removed_surface.removal_events(minimized)),
+            None => self
+                .children
+                .iter_mut()
+                .find_map(|child| child.del_descendant(id, minimized)),
+        }
+    }
+    /// Events generated by the removal of this surface and all its children.
+    fn removal_events(&self, minimized: bool) -> Vec<SurfaceEvent> {
+        self.surfaces_ref()
+            .map(|surface| {
+                let id = surface.id;
+                surface
+                    .buffer
+                    .as_ref()
+                    .filter(|_| !minimized)
+                    .map(|_| SurfaceEvent::BufferDetached { id })
                     .into_iter()
-                    .flatten()
+                    .chain(std::iter::once(SurfaceEvent::Removed { id }))
             })
+            .flatten()
+            .collect()
     }
 
     pub fn children_ref(&self) -> impl Iterator<Item = &Surface> + Clone {
@@ -522,11 +1022,14 @@ impl Surface {
         .chain(self.update_children())
     }
 
-    pub fn surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
-        self.children
-            .iter()
-            .map(|surface| surface.as_ref())
-            .chain(std::iter::once(self))
+    pub fn surfaces_ref(&self) -> Box<dyn Iterator<Item = &Surface> + '_> {
+        Box::new(
+            self.children
+                .iter()
+                .map(|surface| surface.surfaces_ref())
+                .flatten()
+                .chain(std::iter::once(self)),
+        )
     }
 
     /*
@@ -544,17 +1047,21 @@ impl Surface {
         }
     */
     pub fn surface_parent_mut(&mut self, id: usize) -> Option<&mut Surface> {
-        if self.surface_mut(id).is_some() {
+        if self.children.iter().any(|child| child.id == id) {
             Some(self)
         } else {
-            None
+            self.children
+                .iter_mut()
+                .find_map(|child| child.surface_parent_mut(id))
         }
     }
     pub fn surface_parent_ref(&self, id: usize) -> Option<&Surface> {
-        if self.surface_ref(id).is_some() {
+        if self.children.iter().any(|child| child.id == id) {
             Some(self)
         } else {
-            None
+            self.children
+                .iter()
+                .find_map(|child| child.surface_parent_ref(id))
         }
     }
 
@@ -562,10 +1069,7 @@ impl Surface {
         if self.id == id {
             Some(self)
         } else {
-            self.children
-                .iter()
-                .find(|child| child.id == id)
-                .map(|surface| surface.as_ref())
+            self.children.iter().find_map(|child| child.surface_ref(id))
         }
     }
     pub fn surface_mut(&mut self, id: usize) -> Option<&mut Surface> {
@@ -574,8 +1078,7 @@ impl Surface {
         } else {
             self.children
                 .iter_mut()
-                .find(|child| child.id == id)
-                .map(|surface| surface.as_mut())
+                .find_map(|child| child.surface_mut(id))
         }
     }
 
@@ -612,110 +1115,26 @@ impl Surface {
             if let Some((parent_geometry, child_size)) = inner_geometry.clone().zip(child.size()) {
                 match &mut child.kind {
                     SurfaceKind::Popup { state, .. } => {
-                        let mut absolute_anchor = state.anchor.clone();
-                        //absolute_anchor.position = absolute_anchor.position + self.position.clone();
-                        absolute_anchor.position = absolute_anchor.position
-                            + self.position.clone()
-                            + parent_geometry.position.clone();
-
-                        let position = match state.anchor_edges {
-                            ews::Anchor::TopLeft => absolute_anchor.position,
-                            ews::Anchor::BottomLeft => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((0, absolute_anchor.size.height as i32))
-                            }
-                            ews::Anchor::TopRight => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((absolute_anchor.size.width as i32, 0))
-                            }
-                            ews::Anchor::BottomRight => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        absolute_anchor.size.width as i32,
-                                        absolute_anchor.size.height as i32,
-                                    ))
-                            }
-                            ews::Anchor::Top => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        absolute_anchor.size.width as i32 / 2,
-                                        0,
-                                    ))
-                            }
-                            ews::Anchor::Bottom => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        absolute_anchor.size.width as i32 / 2,
-                                        absolute_anchor.size.height as i32,
-                                    ))
-                            }
-                            ews::Anchor::Left => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        0,
-                                        absolute_anchor.size.height as i32 / 2,
-                                    ))
-                            }
-                            ews::Anchor::Right => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        absolute_anchor.size.width as i32,
-                                        absolute_anchor.size.height as i32 / 2,
-                                    ))
-                            }
-                            ews::Anchor::None => {
-                                absolute_anchor.position
-                                    + pal::Offset2D::from((
-                                        absolute_anchor.size.width as i32 / 2,
-                                        absolute_anchor.size.height as i32 / 2,
-                                    ))
-                            }
-                            _ => absolute_anchor.position.clone(),
-                        };
-
-                        let aligned_position = match state.gravity {
-                            ews::Gravity::TopRight => position,
-                            ews::Gravity::BottomRight => {
-                                position - pal::Offset2D::from((0i32, child_size.height as i32))
-                            }
-                            ews::Gravity::TopLeft => {
-                                position - pal::Offset2D::from((child_size.width as i32, 0))
-                            }
-                            ews::Gravity::BottomLeft => {
-                                position
-                                    - pal::Offset2D::from((
-                                        child_size.width as i32,
-                                        child_size.height as i32,
-                                    ))
-                            }
-
-                            ews::Gravity::Bottom => {
-                                position - pal::Offset2D::from((child_size.width as i32 / 2, 0))
-                            }
-                            ews::Gravity::Top => {
-                                position
-                                    - pal::Offset2D::from((child_size.width as i32 / 2, 0))
-                                    - pal::Offset2D::from((0, child_size.height as i32))
-                            }
-                            ews::Gravity::Right => {
-                                position - pal::Offset2D::from((0, child_size.height as i32 / 2))
-                            }
-                            ews::Gravity::Left => {
-                                position
-                                    - pal::Offset2D::from((0, child_size.height as i32 / 2))
-                                    - pal::Offset2D::from((child_size.width as i32, 0))
-                            }
-                            ews::Gravity::None => {
-                                position
-                                    - pal::Offset2D::from((
-                                        child_size.width as i32 / 2,
-                                        child_size.height as i32 / 2,
-                                    ))
-                            }
-                            _ => position,
-                        };
-                        //println!("New position: {}", aligned_position);
-                        events.append(&mut child.r#move(aligned_position, child.depth).collect());
+                        let parent_origin =
+                            self.position.clone() + parent_geometry.position.clone();
+                        let geometry = state.constrained_geometry(&parent_origin, child_size);
+
+                        //Xdg popups are configured relative to the parent window geometry.
+                        let relative_geometry = pal::Rectangle::from((
+                            pal::Position2D::from((
+                                geometry.position.x - parent_origin.x,
+                                geometry.position.y - parent_origin.y,
+                            )),
+                            geometry.size.clone(),
+                        ));
+                        if state.geometry.as_ref() != Some(&relative_geometry) {
+                            state.geometry = Some(relative_geometry.clone());
+                            events.push(SurfaceEvent::PopupConfiguration {
+                                id: child.id,
+                                geometry: relative_geometry,
+                            });
+                        }
+                        events.append(&mut child.r#move(geometry.position, child.depth).collect());
                     }
                     _ => (),
                 }
@@ -873,14 +1292,64 @@ impl Surface {
         }
     }
 
+    pub fn start_interactive_move(
+        &mut self,
+        serial: u32,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let geometry = self.geometry();
+        match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                if let Some(geometry) = geometry {
+                    if state.start_interactive_move(serial, geometry) {
+                        vec![SurfaceEvent::InteractiveMoveStarted {
+                            id: self.id,
+                            serial,
+                        }]
+                        .into_iter()
+                    } else {
+                        Vec::new().into_iter()
+                    }
+                } else {
+                    Vec::new().into_iter()
+                }
+            }
+            SurfaceKind::Popup { .. } => Vec::new().into_iter(),
+        }
+    }
+
+    pub fn stop_interactive_move(
+        &mut self,
+        serial: u32,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                if state.stop_interactive_move(serial) {
+                    vec![SurfaceEvent::InteractiveMoveStopped {
+                        id: self.id,
+                        serial,
+                    }]
+                    .into_iter()
+                } else {
+                    Vec::new().into_iter()
+                }
+            }
+            SurfaceKind::Popup { .. } => Vec::new().into_iter(),
+        }
+    }
+
     pub fn maximize(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
-        self.geometry()
+        //The inner size is saved, as for tiling and fullscreen.
+        let original = self.inner_geometry().map(|inner_geometry| {
+            pal::Rectangle::from((self.position.clone(), inner_geometry.size.clone()))
+        });
+        original
             .map(|geometry| match &mut self.kind {
                 SurfaceKind::Toplevel { state, .. } => {
-                    state
+                    let altered_state = state
                         .altered_state
-                        .get_or_insert(AlteredState::new(geometry))
-                        .maximized = true;
+                        .get_or_insert(AlteredState::new(geometry));
+                    altered_state.maximized = true;
+                    altered_state.tiled = None;
                     Some(SurfaceEvent::Maximized { id: self.id })
                 }
                 SurfaceKind::Popup { .. } => None,
@@ -935,25 +1404,274 @@ impl Surface {
         });
     }
 */
-    pub fn minimize(&mut self) {
-        self.geometry().map(|geometry| match &mut self.kind {
-            SurfaceKind::Toplevel { state, .. } => {
-                state
+    pub fn tile(
+        &mut self,
+        zone: TileZone,
+        geometry: pal::Rectangle<i32, u32>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let id = self.id;
+        let original = self.inner_geometry().map(|inner_geometry| {
+            pal::Rectangle::from((self.position.clone(), inner_geometry.size.clone()))
+        });
+        let inner_offset = self
+            .inner_geometry()
+            .map(|inner_geometry| {
+                pal::Offset2D::from((inner_geometry.position.x, inner_geometry.position.y))
+            })
+            .unwrap_or(pal::Offset2D::from((0, 0)));
+        let tiled = match (&mut self.kind, original) {
+            (SurfaceKind::Toplevel { state, .. }, Some(original)) => {
+                let altered_state = state
                     .altered_state
-                    .get_or_insert(AlteredState::new(geometry))
-                    .minimized = true;
+                    .get_or_insert(AlteredState::new(original.clone()));
+                //The size before the first tiling is the one to be restored
+                if !altered_state.is_tiled() && !altered_state.maximized {
+                    altered_state.original = original;
+                }
+                altered_state.tiled = Some(zone);
+                true
             }
-            SurfaceKind::Popup { .. } => (),
-        });
-    }
-}
-impl Ord for Surface {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.depth.cmp(&other.depth)
+            _ => false,
+        };
+        if tiled {
+            let depth = self.depth;
+            std::iter::once(SurfaceEvent::Tiled { id, zone })
+                .chain(self.r#move(geometry.position - inner_offset, depth))
+                .chain(std::iter::once(SurfaceEvent::Configuration {
+                    id,
+                    size: geometry.size,
+                }))
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        }
+        .into_iter()
     }
-}
-impl PartialOrd for Surface {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+
+    /// Restore the original size of a tiled surface dragged away by the cursor.
+    /// The surface is moved so that the cursor keeps the same relative horizontal position on it.
+    pub fn untile(
+        &mut self,
+        cursor: &pal::Position2D<i32>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let id = self.id;
+        let inner_geometry = match self.inner_geometry() {
+            Some(inner_geometry) => inner_geometry.clone(),
+            None => return Vec::new().into_iter(),
+        };
+        let inner_position = self.position.clone() + inner_geometry.position.clone();
+        let original = match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                let original = state
+                    .altered_state
+                    .as_mut()
+                    .filter(|altered_state| altered_state.is_tiled())
+                    .map(|altered_state| {
+                        altered_state.tiled = None;
+                        altered_state.original.clone()
+                    });
+                if state.is_empty() {
+                    state.altered_state = None;
+                }
+                original
+            }
+            SurfaceKind::Popup { .. } => None,
+        };
+        let original = match original {
+            Some(original) => original,
+            None => return Vec::new().into_iter(),
+        };
+
+        let grab_x = (cursor.x - inner_position.x) as i64 * original.size.width as i64
+            / inner_geometry.size.width.max(1) as i64;
+        let grab_y = (cursor.y - inner_position.y).clamp(0, original.size.height as i32);
+        let move_offset = pal::Offset2D::from((
+            grab_x as i32 + inner_geometry.position.x,
+            grab_y + inner_geometry.position.y,
+        ));
+        let position = cursor.clone() - move_offset.clone();
+        if let SurfaceKind::Toplevel { state, .. } = &mut self.kind {
+            if let Some(altered_state) = state.altered_state.as_mut() {
+                altered_state.move_offset = Some(move_offset);
+            }
+        }
+
+        let depth = self.depth;
+        std::iter::once(SurfaceEvent::Untiled { id })
+            .chain(std::iter::once(SurfaceEvent::Configuration {
+                id,
+                size: original.size,
+            }))
+            .chain(self.r#move(position, depth))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    pub fn minimize(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.geometry()
+            .map(|geometry| match &mut self.kind {
+                SurfaceKind::Toplevel { state, .. } => {
+                    let altered_state = state
+                        .altered_state
+                        .get_or_insert(AlteredState::new(geometry));
+                    if altered_state.minimized {
+                        None
+                    } else {
+                        altered_state.minimized = true;
+                        Some(SurfaceEvent::Minimized { id: self.id })
+                    }
+                }
+                SurfaceKind::Popup { .. } => None,
+            })
+            .flatten()
+            .into_iter()
+    }
+
+    pub fn restore(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let restored = match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                let restored = state.altered_state.as_mut().map(|altered_state| {
+                    let minimized = altered_state.minimized;
+                    altered_state.minimized = false;
+                    minimized
+                }) == Some(true);
+                if state.is_empty() {
+                    state.altered_state = None;
+                }
+                restored
+            }
+            SurfaceKind::Popup { .. } => false,
+        };
+        if restored {
+            //Minimized surfaces are not present in the screen task,
+            //so their buffers have to be attached again.
+            std::iter::once(SurfaceEvent::Restored { id: self.id })
+                .chain(
+                    self.surfaces_ref()
+                        .filter_map(|surface| {
+                            surface.buffer.as_ref().map(|buffer| {
+                                let id = surface.id;
+                                let geometry =
+                                    pal::Rectangle::from((surface.position.clone(), buffer.size()));
+                                vec![
+                                    SurfaceEvent::BufferAttached {
+                                        id,
+                                        handle: buffer.handle.clone(),
+                                        inner_geometry: buffer.geometry.clone(),
+                                        geometry,
+                                    },
+                                    SurfaceEvent::Moved {
+                                        id,
+                                        position: surface.position.clone(),
+                                        depth: surface.depth,
+                                    },
+                                ]
+                            })
+                        })
+                        .flatten()
+                        .collect::<Vec<_>>(),
+                )
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        }
+        .into_iter()
+    }
+
+    pub fn fullscreen(
+        &mut self,
+        output: Option<usize>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        //The inner size is saved instead of the buffer one,
+        //so that restoring it does not grow the window by its decorations.
+        //Surfaces without a buffer yet are restored to a null size, which lets the client choose it.
+        let size = self
+            .inner_geometry()
+            .map(|inner_geometry| inner_geometry.size.clone())
+            .unwrap_or(pal::Size2D::from((0, 0)));
+        let original = pal::Rectangle::from((self.position.clone(), size));
+        match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                let altered_state = state
+                    .altered_state
+                    .get_or_insert(AlteredState::new(original.clone()));
+                //Tiled surfaces keep the geometry they had before being tiled.
+                if !altered_state.maximized
+                    && !altered_state.fullscreen
+                    && !altered_state.is_tiled()
+                {
+                    altered_state.original = original;
+                }
+                altered_state.fullscreen = true;
+                Some(SurfaceEvent::Fullscreened {
+                    id: self.id,
+                    output,
+                })
+            }
+            SurfaceKind::Popup { .. } => None,
+        }
+        .into_iter()
+    }
+
+    pub fn unfullscreen(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let id = self.id;
+        let restore = match &mut self.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                let restore = state
+                    .altered_state
+                    .as_mut()
+                    .map(|altered_state| {
+                        if altered_state.fullscreen {
+                            altered_state.fullscreen = false;
+                            Some((
+                                altered_state.original.clone(),
+                                altered_state.maximized,
+                                altered_state.is_tiled(),
+                            ))
+                        } else {
+                            None
+                        }
+                    })
+                    .flatten();
+                if state.is_empty() {
+                    state.altered_state = None;
+                }
+                restore
+            }
+            SurfaceKind::Popup { .. } => None,
+        };
+        restore
+            .map(|(original, maximized, tiled)| {
+                let events = std::iter::once(SurfaceEvent::Unfullscreened { id });
+                if maximized {
+                    //Let the maximize logic compute the geometry again on the current output.
+                    events
+                        .chain(std::iter::once(SurfaceEvent::Maximized { id }))
+                        .collect::<Vec<_>>()
+                } else if tiled {
+                    //The tile geometry depends on the output, the geometry manager tiles the surface again.
+                    events.collect::<Vec<_>>()
+                } else {
+                    events
+                        .chain(self.r#move(original.position, self.depth))
+                        .chain(std::iter::once(SurfaceEvent::Configuration {
+                            id,
+                            size: original.size,
+                        }))
+                        .collect::<Vec<_>>()
+                }
+            })
+            .into_iter()
+            .flatten()
+    }
+}
+impl Ord for Surface {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.depth.cmp(&other.depth)
+    }
+}
+impl PartialOrd for Surface {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.depth.cmp(&other.depth))
     }
 }
@@ -971,32 +1689,229 @@ pub struct SurfaceManager {
     cursor_surfaces: VecDeque<Surface>,
     surfaces: VecDeque<Surface>,
     border_grace: u32,
+    snap_threshold: u32,
+    tiling_threshold: u32,
+    tiling_preview: Option<(usize, TileZone, pal::Rectangle<i32, u32>)>,
+    min_surface_size: pal::Size2D<u32>,
     active_surface: Option<usize>,
 }
 impl SurfaceManager {
-    const CURSOR_MIN_DEPTH: u32 = 0;
+    pub const CURSOR_MIN_DEPTH: u32 = 0;
     const SURFACE_MIN_DEPTH: u32 = 16;
     pub fn new() -> Self {
         let cursor_surfaces = VecDeque::new();
         let surfaces = VecDeque::new();
         let border_grace = 10;
+        let snap_threshold = border_grace;
+        let tiling_threshold = border_grace;
+        let tiling_preview = None;
+        let min_surface_size = pal::Size2D::from((32, 32));
         let active_surface = None;
         Self {
             cursor_surfaces,
             surfaces,
             border_grace,
+            snap_threshold,
+            tiling_threshold,
+            tiling_preview,
+            min_surface_size,
             active_surface,
         }
     }
 
+    /// Set the minimum size any surface can be interactively resized to.
+    pub fn set_min_surface_size(&mut self, min_surface_size: pal::Size2D<u32>) {
+        self.min_surface_size = min_surface_size;
+    }
+
+    /// Set the distance in pixels from the output edges under which a dragged surface gets tiled.
+    pub fn set_tiling_threshold(&mut self, tiling_threshold: u32) {
+        self.tiling_threshold = tiling_threshold;
+    }
+    pub fn tiling_threshold(&self) -> u32 {
+        self.tiling_threshold
+    }
+
+    /// Get the depth of the tiling preview, between the cursor surfaces and the topmost surface.
+    pub fn preview_depth(&self) -> u32 {
+        self.cursor_surfaces.len() as u32
+    }
+
+    /// Get the tiling zone (and its area) previewed for a moving surface.
+    pub fn tiling_preview(&self, id: usize) -> Option<(TileZone, pal::Rectangle<i32, u32>)> {
+        self.tiling_preview
+            .as_ref()
+            .filter(|(preview_id, _, _)| *preview_id == id)
+            .map(|(_, zone, geometry)| (*zone, geometry.clone()))
+    }
+
+    /// Set the tiling zone previewed for a moving surface, emitting events only if it changed.
+    pub fn set_tiling_preview(
+        &mut self,
+        id: usize,
+        preview: Option<(TileZone, pal::Rectangle<i32, u32>)>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let current = self
+            .tiling_preview
+            .as_ref()
+            .map(|(preview_id, zone, _)| (*preview_id, *zone));
+        let unchanged = current == preview.as_ref().map(|(zone, _)| (id, *zone));
+        let not_owned = preview.is_none() && current.map(|(preview_id, _)| preview_id) != Some(id);
+        if unchanged || not_owned {
+            return Vec::new().into_iter();
+        }
+        let mut events = Vec::new();
+        if let Some((preview_id, _, _)) = self.tiling_preview.take() {
+            events.push(SurfaceEvent::TilingPreview {
+                id: preview_id,
+                geometry: None,
+            });
+        }
+        if let Some((zone, geometry)) = preview {
+            self.tiling_preview = Some((id, zone, geometry.clone()));
+            events.push(SurfaceEvent::TilingPreview {
+                id,
+                geometry: Some(geometry),
+            });
+        }
+        events.into_iter()
+    }
+
+    pub fn tile_surface(
+        &mut self,
+        id: usize,
+        zone: TileZone,
+        geometry: pal::Rectangle<i32, u32>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.tile(zone, geometry))
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn untile_surface(
+        &mut self,
+        id: usize,
+        cursor: &pal::Position2D<i32>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.untile(cursor))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Set the distance in pixels under which a moving surface snaps to an edge.
+    /// A threshold of 0 disables snapping.
+    pub fn set_snap_threshold(&mut self, snap_threshold: u32) {
+        self.snap_threshold = snap_threshold;
+    }
+
+    /// Snap the position of a moving surface to the edges of `bounds` and of the other visible surfaces,
+    /// if closer than the snap threshold.
+    /// The inner geometry is used, so client side decorations like shadows are not taken into account.
+    pub fn snap_position(
+        &self,
+        id: usize,
+        position: pal::Position2D<i32>,
+        bounds: &[pal::Rectangle<i32, u32>],
+    ) -> pal::Position2D<i32> {
+        let threshold = self.snap_threshold as i32;
+        let inner_geometry = match self
+            .surface_ref(id)
+            .map(|surface| surface.inner_geometry())
+            .flatten()
+        {
+            Some(inner_geometry) if threshold > 0 => inner_geometry.clone(),
+            _ => return position,
+        };
+        let inner_position = position.clone() + inner_geometry.position.clone();
+        let inner_size = inner_geometry.size.clone();
+
+        let neighbours = self
+            .surfaces
+            .iter()
+            .filter(|surface| surface.id != id && !surface.is_minimized())
+            .filter_map(|surface| {
+                surface.inner_geometry().map(|inner_geometry| {
+                    pal::Rectangle::from((
+                        surface.position.clone() + inner_geometry.position.clone(),
+                        inner_geometry.size.clone(),
+                    ))
+                })
+            });
+        let rectangles = bounds.iter().cloned().chain(neighbours).collect::<Vec<_>>();
+
+        //Only edges that face the surface on the other axis are taken into account
+        let x_offset = Self::snap_axis(
+            (inner_position.x, inner_size.width as i32),
+            rectangles
+                .iter()
+                .filter(|rectangle| {
+                    Self::overlaps(
+                        (inner_position.y, inner_size.height as i32),
+                        (rectangle.position.y, rectangle.size.height as i32),
+                        threshold,
+                    )
+                })
+                .map(|rectangle| (rectangle.position.x, rectangle.size.width as i32)),
+            threshold,
+        );
+        let y_offset = Self::snap_axis(
+            (inner_position.y, inner_size.height as i32),
+            rectangles
+                .iter()
+                .filter(|rectangle| {
+                    Self::overlaps(
+                        (inner_position.x, inner_size.width as i32),
+                        (rectangle.position.x, rectangle.size.width as i32),
+                        threshold,
+                    )
+                })
+                .map(|rectangle| (rectangle.position.y, rectangle.size.height as i32)),
+            threshold,
+        );
+
+        pal::Position2D::from((position.x + x_offset, position.y + y_offset))
+    }
+
+    /// Get the smallest offset that aligns one of the edges of `segment` to one of the edges of `others`,
+    /// or 0 if none is within `threshold`.
+    fn snap_axis(
+        segment: (i32, i32),
+        others: impl Iterator<Item = (i32, i32)>,
+        threshold: i32,
+    ) -> i32 {
+        let (start, length) = segment;
+        others
+            .map(|(other_start, other_length)| {
+                let edges = [other_start, other_start + other_length];
+                edges
+                    .iter()
+                    .map(|edge| [edge - start, edge - (start + length)])
+                    .flatten()
+                    .collect::<Vec<_>>()
+            })
+            .flatten()
+            .filter(|offset| offset.abs() <= threshold)
+            .min_by_key(|offset| offset.abs())
+            .unwrap_or(0)
+    }
+
+    fn overlaps(first: (i32, i32), second: (i32, i32), threshold: i32) -> bool {
+        first.0 <= second.0 + second.1 + threshold && second.0 <= first.0 + first.1 + threshold
+    }
+
     pub fn get_surface_at(&mut self, position: &pal::Position2D<i32>) -> Option<&Surface> {
-        self.surfaces_ref().find(|surface| {
+        let border_grace = self.border_grace;
+        self.visible_surfaces_ref().find(|surface| {
             if let Some(buffer) = &surface.buffer {
+                let fullscreen = surface.state().map(|state| state.is_fullscreen()) == Some(true);
+                let border_grace = if fullscreen { 0 } else { border_grace };
                 pal::Rectangle::from((
                     surface.position.clone() + buffer.geometry.position.clone()
-                        - pal::Offset2D::from((self.border_grace as i32, self.border_grace as i32)),
+                        - pal::Offset2D::from((border_grace as i32, border_grace as i32)),
                     buffer.geometry.size.clone()
-                        + pal::Offset2D::from((self.border_grace * 2, self.border_grace * 2)),
+                        + pal::Offset2D::from((border_grace * 2, border_grace * 2)),
                 ))
                 .contains(&position)
             } else {
@@ -1015,6 +1930,7 @@ impl SurfaceManager {
         let max_size = pal::Size2D::from((0, 0));
         let depth = 0;
         let buffer = None;
+        let output = None;
         let children = VecDeque::new();
 
         let surface = Surface {
@@ -1025,6 +1941,7 @@ impl SurfaceManager {
             max_size,
             position,
             depth,
+            output,
             children,
         };
 
@@ -1079,11 +1996,10 @@ impl SurfaceManager {
             self.surfaces
                 .remove(top_level_surface_to_be_removed)
                 .map(|removed_surface| {
+                    let minimized = removed_surface.is_minimized();
                     removed_surface
-                        .buffer
-                        .map(|_| SurfaceEvent::BufferDetached { id })
+                        .removal_events(minimized)
                         .into_iter()
-                        .chain(vec![SurfaceEvent::Removed { id }])
                         .chain({
                             if self.active_surface == Some(id) {
                                 self.active_surface = None;
@@ -1147,11 +2063,14 @@ impl SurfaceManager {
         handle: ews::WlBuffer,
         inner_geometry: pal::Rectangle<i32, u32>,
         suggested_size: pal::Size2D<u32>,
+        scale: u32,
     ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let scale = scale.max(1);
         self.surface_mut(id)
             .map(|surface| {
                 let event = if let Some(mut buffer) = surface.buffer.as_mut() {
                     buffer.handle = handle.clone();
+                    buffer.scale = scale;
                     let geometry = pal::Rectangle::from((surface.position.clone(), buffer.size()));
                     SurfaceEvent::BufferReplaced {
                         id,
@@ -1163,6 +2082,7 @@ impl SurfaceManager {
                     surface.buffer = Some(Buffer {
                         handle: handle.clone(),
                         geometry: inner_geometry.clone(),
+                        scale,
                     });
                     let geometry = pal::Rectangle::from((surface.position.clone(), suggested_size));
                     SurfaceEvent::BufferAttached {
@@ -1206,31 +2126,31 @@ impl SurfaceManager {
             .flatten()
     }
 
-    pub fn focus_surface(
-        &mut self,
-        id: Option<usize>,
-    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
-        let events = id
-            .map(|id| {
-                self.surfaces
-                    .iter()
-                    .position(|surface| surface.id == id)
-                    .map(|position| {
-                        self.surfaces.remove(position).map(|surface| {
-                            self.surfaces.push_front(surface);
-                            self.update_surfaces_depth()
-                        })
-                    })
-                    .flatten()
+    pub fn raise_surface(&mut self, id: usize) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surfaces
+            .iter()
+            .position(|surface| surface.id == id)
+            .map(|position| {
+                self.surfaces.remove(position).map(|surface| {
+                    self.surfaces.push_front(surface);
+                    self.update_surfaces_depth()
+                })
             })
             .flatten()
             .into_iter()
-            .flatten();
+            .flatten()
+    }
 
+    /// Activate a surface, deactivating the previous one.
+    /// Raising the surface is up to the caller, since focus and raise can be configured separately.
+    pub fn focus_surface(
+        &mut self,
+        id: Option<usize>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
         let current_active_surface = self.active_surface.take();
         self.active_surface = id;
 
-        events
+        std::iter::empty()
             .chain(
                 current_active_surface.map(|current_active_surface| SurfaceEvent::Deactivated {
                     id: current_active_surface,
@@ -1255,6 +2175,91 @@ impl SurfaceManager {
         self.surface_mut(id).map(|surface|surface.unmaximize()).into_iter().flatten()
     }
 
+    pub fn minimize_surface(&mut self, id: usize) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.minimize())
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn restore_surface(&mut self, id: usize) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        let events = self
+            .surface_mut(id)
+            .map(|surface| surface.restore().collect::<Vec<_>>())
+            .unwrap_or(Vec::new());
+        if events.is_empty() {
+            events
+        } else {
+            events
+                .into_iter()
+                .chain(self.raise_surface(id))
+                .collect::<Vec<_>>()
+        }
+        .into_iter()
+    }
+
+    pub fn fullscreen_surface(
+        &mut self,
+        id: usize,
+        output: Option<usize>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.fullscreen(output))
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn unfullscreen_surface(
+        &mut self,
+        id: usize,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.unfullscreen())
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn interactive_move_start(
+        &mut self,
+        id: usize,
+        serial: u32,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.start_interactive_move(serial))
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn interactive_move_surface(
+        &mut self,
+        id: usize,
+        serial: u32,
+        position: pal::Position2D<i32>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| {
+                if surface.kind.check_move(serial) {
+                    Some(surface.r#move(position, surface.depth))
+                } else {
+                    None
+                }
+            })
+            .flatten()
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn interactive_move_end(
+        &mut self,
+        id: usize,
+        serial: u32,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .map(|surface| surface.stop_interactive_move(serial))
+            .into_iter()
+            .flatten()
+    }
+
     pub fn interactive_resize_start(
         &mut self,
         id: usize,
@@ -1278,7 +2283,7 @@ impl SurfaceManager {
                 if surface.kind.check_resize(serial) {
                     Some(SurfaceEvent::Configuration {
                         id,
-                        size: inner_size,
+                        size: surface.clamp_size(inner_size, &self.min_surface_size),
                     })
                 } else {
                     None
@@ -1335,6 +2340,33 @@ impl SurfaceManager {
     pub fn surfaces_mut(&mut self) -> impl Iterator<Item = &mut Surface> {
         self.surfaces.iter_mut()
     }
+    /// Get the top-level surfaces, without their children.
+    pub fn toplevel_surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
+        self.surfaces.iter()
+    }
+    /// Get the references of all the surfaces that are not minimized, together with their children.
+    pub fn visible_surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
+        self.surfaces
+            .iter()
+            .filter(|surface| !surface.is_minimized())
+            .map(|surface| surface.surfaces_ref())
+            .flatten()
+    }
+    pub fn minimized_surfaces_ref(&self) -> impl Iterator<Item = &Surface> {
+        self.surfaces
+            .iter()
+            .filter(|surface| surface.is_minimized())
+    }
+    pub fn is_surface_visible(&self, id: usize) -> bool {
+        self.surfaces
+            .iter()
+            .find(|surface| surface.surface_ref(id).is_some())
+            .map(|surface| !surface.is_minimized())
+            == Some(true)
+    }
+    pub fn active_surface(&self) -> Option<usize> {
+        self.active_surface
+    }
 
     pub fn surface_ref(&self, id: usize) -> Option<&Surface> {
         for surface in &self.surfaces {
@@ -1355,11 +2387,64 @@ impl SurfaceManager {
         return None;
     }
 
+    /// Set the output a surface is shown on, notifying the output it left and the one it entered.
+    pub fn set_surface_output(
+        &mut self,
+        id: usize,
+        output: Option<usize>,
+    ) -> impl Iterator<Item = SurfaceEvent> + Clone {
+        self.surface_mut(id)
+            .filter(|surface| surface.output != output)
+            .map(|surface| {
+                let previous = std::mem::replace(&mut surface.output, output);
+                previous
+                    .map(|output| SurfaceEvent::OutputLeft { id, output })
+                    .into_iter()
+                    .chain(output.map(|output| SurfaceEvent::OutputEntered { id, output }))
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+    }
+
     pub fn surface_parent_mut(&mut self, id: usize) -> Option<&mut Surface> {
         self.surfaces
             .iter_mut()
             .find_map(|surface| surface.surface_parent_mut(id))
     }
+    pub fn surface_parent_ref(&self, id: usize) -> Option<&Surface> {
+        self.surfaces
+            .iter()
+            .find_map(|surface| surface.surface_parent_ref(id))
+    }
+
+    /// Get the id of the top-level surface containing the specified surface in its tree.
+    pub fn toplevel_of(&self, id: usize) -> Option<usize> {
+        self.surfaces
+            .iter()
+            .find(|surface| surface.surface_ref(id).is_some())
+            .map(|surface| surface.id)
+    }
+
+    /// Set the area a popup should be constrained into.
+    pub fn set_popup_bounds(&mut self, id: usize, bounds: pal::Rectangle<i32, u32>) {
+        self.surface_mut(id).map(|surface| match &mut surface.kind {
+            SurfaceKind::Popup { state, .. } => state.bounds = Some(bounds),
+            SurfaceKind::Toplevel { .. } => (),
+        });
+    }
+
+    /// Change the geometry a top-level surface is restored to once it leaves its altered states.
+    pub fn set_original_geometry(&mut self, id: usize, original: pal::Rectangle<i32, u32>) {
+        self.surface_mut(id).map(|surface| match &mut surface.kind {
+            SurfaceKind::Toplevel { state, .. } => {
+                if let Some(altered_state) = state.altered_state.as_mut() {
+                    altered_state.original = original;
+                }
+            }
+            SurfaceKind::Popup { .. } => (),
+        });
+    }
 
     fn update_depth(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
         std::iter::empty()
@@ -1388,7 +2473,8 @@ impl SurfaceManager {
             .into_iter()
     }
     fn update_surfaces_depth(&mut self) -> impl Iterator<Item = SurfaceEvent> + Clone {
-        let depth_offset = self.cursor_surfaces.len() as u32;
+        //The depth right above the surfaces is kept free for the tiling preview.
+        let depth_offset = self.preview_depth() + 1;
 
         let mut depth = depth_offset;
         self.surfaces
@@ -1461,3 +2547,208 @@ impl SurfaceManager {
         events.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popup(anchor_edges: ews::Anchor, gravity: ews::Gravity) -> PopupState {
+        PopupState {
+            anchor: pal::Rectangle::from((
+                pal::Position2D::from((100, 100)),
+                pal::Size2D::from((20, 10)),
+            )),
+            anchor_edges,
+            gravity,
+            constraint_adjustment: ews::ConstraintAdjustment::empty(),
+            offset: pal::Offset2D::from((0, 0)),
+            reactive: false,
+            bounds: Some(pal::Rectangle::from((
+                pal::Position2D::from((0, 0)),
+                pal::Size2D::from((200, 200)),
+            ))),
+            geometry: None,
+        }
+    }
+
+    fn rectangle(geometry: &pal::Rectangle<i32, u32>) -> (i32, i32, u32, u32) {
+        (
+            geometry.position.x,
+            geometry.position.y,
+            geometry.size.width,
+            geometry.size.height,
+        )
+    }
+
+    #[test]
+    fn constrain_axis_keeps_fitting_popups() {
+        assert_eq!(
+            PopupState::constrain_axis((10, 50), 0, (0, 100), true, true, true),
+            (10, 50)
+        );
+    }
+
+    #[test]
+    fn constrain_axis_flips_first() {
+        assert_eq!(
+            PopupState::constrain_axis((80, 50), 20, (0, 100), true, true, true),
+            (20, 50)
+        );
+    }
+
+    #[test]
+    fn constrain_axis_slides_when_flipping_does_not_fit() {
+        assert_eq!(
+            PopupState::constrain_axis((80, 50), 120, (0, 100), true, true, false),
+            (50, 50)
+        );
+        assert_eq!(
+            PopupState::constrain_axis((-10, 50), 0, (0, 100), false, true, false),
+            (0, 50)
+        );
+    }
+
+    #[test]
+    fn constrain_axis_resizes_as_last_resort() {
+        assert_eq!(
+            PopupState::constrain_axis((80, 50), 0, (0, 100), false, false, true),
+            (80, 20)
+        );
+        assert_eq!(
+            PopupState::constrain_axis((80, 50), 0, (0, 100), false, false, false),
+            (80, 50)
+        );
+    }
+
+    #[test]
+    fn constrained_geometry_without_bounds_is_not_adjusted() {
+        let mut state = popup(ews::Anchor::BottomRight, ews::Gravity::BottomRight);
+        state.bounds = None;
+        state.constraint_adjustment = ews::ConstraintAdjustment::all();
+        let geometry = state
+            .constrained_geometry(&pal::Position2D::from((0, 0)), pal::Size2D::from((150, 50)));
+        assert_eq!(rectangle(&geometry), (120, 110, 150, 50));
+    }
+
+    #[test]
+    fn constrained_geometry_flips_around_the_anchor() {
+        let mut state = popup(ews::Anchor::BottomRight, ews::Gravity::BottomRight);
+        state.constraint_adjustment = ews::ConstraintAdjustment::FlipX;
+        let geometry =
+            state.constrained_geometry(&pal::Position2D::from((0, 0)), pal::Size2D::from((90, 50)));
+        assert_eq!(rectangle(&geometry), (10, 110, 90, 50));
+    }
+
+    #[test]
+    fn constrained_geometry_applies_the_offset() {
+        let mut state = popup(ews::Anchor::BottomLeft, ews::Gravity::BottomRight);
+        state.offset = pal::Offset2D::from((5, -3));
+        let geometry =
+            state.constrained_geometry(&pal::Position2D::from((0, 0)), pal::Size2D::from((50, 50)));
+        assert_eq!(rectangle(&geometry), (105, 107, 50, 50));
+    }
+
+    #[test]
+    fn constrained_geometry_flips_the_offset_with_the_axis() {
+        let mut state = popup(ews::Anchor::BottomRight, ews::Gravity::BottomRight);
+        state.offset = pal::Offset2D::from((5, 0));
+        state.constraint_adjustment = ews::ConstraintAdjustment::FlipX;
+        let geometry =
+            state.constrained_geometry(&pal::Position2D::from((0, 0)), pal::Size2D::from((90, 50)));
+        assert_eq!(rectangle(&geometry), (5, 110, 90, 50));
+    }
+
+    #[test]
+    fn constrained_geometry_is_relative_to_the_parent_origin() {
+        //Nested popups are anchored to their parent popup, wherever it is.
+        let mut state = popup(ews::Anchor::BottomLeft, ews::Gravity::BottomRight);
+        state.bounds = None;
+        let geometry = state.constrained_geometry(
+            &pal::Position2D::from((30, 40)),
+            pal::Size2D::from((50, 50)),
+        );
+        assert_eq!(rectangle(&geometry), (130, 150, 50, 50));
+    }
+
+    #[test]
+    fn snap_axis_aligns_the_nearest_edge() {
+        //The end of the segment is 2 pixels away from the start of the other one.
+        assert_eq!(
+            SurfaceManager::snap_axis((0, 98), vec![(100, 50)].into_iter(), 10),
+            2
+        );
+        //The start of the segment is 3 pixels past the end of the other one.
+        assert_eq!(
+            SurfaceManager::snap_axis((53, 20), vec![(0, 50), (200, 10)].into_iter(), 10),
+            -3
+        );
+    }
+
+    #[test]
+    fn snap_axis_ignores_edges_past_the_threshold() {
+        assert_eq!(
+            SurfaceManager::snap_axis((0, 80), vec![(100, 50)].into_iter(), 10),
+            0
+        );
+        assert_eq!(
+            SurfaceManager::snap_axis((0, 80), std::iter::empty(), 10),
+            0
+        );
+    }
+
+    #[test]
+    fn overlaps_includes_the_threshold() {
+        assert!(SurfaceManager::overlaps((0, 10), (15, 10), 5));
+        assert!(!SurfaceManager::overlaps((0, 10), (16, 10), 5));
+    }
+
+    fn output() -> pal::Rectangle<i32, u32> {
+        pal::Rectangle::from((
+            pal::Position2D::from((0, 0)),
+            pal::Size2D::from((1001, 800)),
+        ))
+    }
+
+    #[test]
+    fn tile_zone_from_position() {
+        let zone = |x, y| TileZone::from_position(&pal::Position2D::from((x, y)), &output(), 10);
+        assert_eq!(zone(5, 400), Some(TileZone::Left));
+        assert_eq!(zone(995, 400), Some(TileZone::Right));
+        assert_eq!(zone(5, 5), Some(TileZone::TopLeft));
+        assert_eq!(zone(995, 5), Some(TileZone::TopRight));
+        assert_eq!(zone(5, 795), Some(TileZone::BottomLeft));
+        assert_eq!(zone(995, 795), Some(TileZone::BottomRight));
+        assert_eq!(zone(500, 3), Some(TileZone::Maximized));
+        assert_eq!(zone(500, 795), None);
+        assert_eq!(zone(500, 400), None);
+    }
+
+    #[test]
+    fn tile_zone_geometry() {
+        let geometry = |zone: TileZone| rectangle(&zone.geometry(&output()));
+        assert_eq!(geometry(TileZone::Left), (0, 0, 500, 800));
+        //The odd pixel goes to the right half, so that the halves cover the whole output.
+        assert_eq!(geometry(TileZone::Right), (500, 0, 501, 800));
+        assert_eq!(geometry(TileZone::TopLeft), (0, 0, 500, 400));
+        assert_eq!(geometry(TileZone::BottomRight), (500, 400, 501, 400));
+        assert_eq!(geometry(TileZone::Maximized), (0, 0, 1001, 800));
+    }
+
+    #[test]
+    fn clamp_length_follows_the_client_limits() {
+        assert_eq!(Surface::clamp_length(50, 100, 300, 1), 100);
+        assert_eq!(Surface::clamp_length(400, 100, 300, 1), 300);
+        assert_eq!(Surface::clamp_length(200, 100, 300, 1), 200);
+    }
+
+    #[test]
+    fn clamp_length_without_maximum() {
+        assert_eq!(Surface::clamp_length(5000, 100, 0, 1), 5000);
+    }
+
+    #[test]
+    fn clamp_length_prefers_the_compositor_minimum() {
+        assert_eq!(Surface::clamp_length(0, 0, 0, 1), 1);
+        assert_eq!(Surface::clamp_length(50, 10, 20, 32), 32);
+    }
+}
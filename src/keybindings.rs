@@ -0,0 +1,327 @@
+//! Structures and enumerations related to the compositor keybindings.
+//!
+//! Keybindings are configured in a plain text file, one binding per line.
+//! Modifiers are joined to the key name with `+`, keys are named as in xkbcommon:
+//! ```text
+//! logo+q = close
+//! logo+m = maximize
+//! logo+Tab = focus-next
+//! logo+shift+Tab = focus-previous
+//! logo+o = switch-output
+//! logo+Return = spawn foot
+//! ```
+
+use std::path::PathBuf;
+
+bitflags::bitflags! {
+    /// Modifiers that must be held for a keybinding to trigger.
+    pub struct Modifiers: u8 {
+        const CTRL = 0b0001;
+        const ALT = 0b0010;
+        const SHIFT = 0b0100;
+        const LOGO = 0b1000;
+    }
+}
+impl From<&ews::ModifiersState> for Modifiers {
+    fn from(state: &ews::ModifiersState) -> Self {
+        let mut modifiers = Self::empty();
+        modifiers.set(Self::CTRL, state.ctrl);
+        modifiers.set(Self::ALT, state.alt);
+        modifiers.set(Self::SHIFT, state.shift);
+        modifiers.set(Self::LOGO, state.logo);
+        modifiers
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Actions the compositor can perform when a keybinding triggers.
+pub enum Action {
+    /// Ask the active surface to close.
+    Close,
+    /// Toggle the maximization of the active surface.
+    Maximize,
+    /// Move the keyboard focus to the next surface.
+    FocusNext,
+    /// Move the keyboard focus to the previous surface.
+    FocusPrevious,
+    /// Move the cursor and the keyboard focus to the next output.
+    SwitchOutput,
+    /// Run a shell command.
+    Spawn(String),
+}
+impl Action {
+    /// Parse an action name, followed by the command for `spawn`.
+    fn parse(value: &str) -> Option<Self> {
+        let (name, argument) = match value.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (value, ""),
+        };
+        match (name, argument) {
+            ("close", "") => Some(Self::Close),
+            ("maximize", "") => Some(Self::Maximize),
+            ("focus-next", "") => Some(Self::FocusNext),
+            ("focus-previous", "") => Some(Self::FocusPrevious),
+            ("switch-output", "") => Some(Self::SwitchOutput),
+            ("spawn", command) if !command.is_empty() => Some(Self::Spawn(command.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Combination of modifiers and unmodified keysym.
+pub struct KeyBinding {
+    pub modifiers: Modifiers,
+    pub keysym: u32,
+}
+impl KeyBinding {
+    pub fn new(modifiers: Modifiers, keysym: u32) -> Self {
+        Self { modifiers, keysym }
+    }
+
+    /// Parse a combination like `logo+shift+Tab`, the key being the last element.
+    fn parse(value: &str) -> Option<Self> {
+        let mut elements = value.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = elements.pop()?;
+        let mut modifiers = Modifiers::empty();
+        for element in elements {
+            modifiers |= match element.to_lowercase().as_str() {
+                "ctrl" => Modifiers::CTRL,
+                "alt" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                "logo" => Modifiers::LOGO,
+                _ => return None,
+            };
+        }
+        let keysym = xkbcommon::xkb::keysym_from_name(key, xkbcommon::xkb::KEYSYM_NO_FLAGS);
+        if keysym == ews::keysyms::KEY_NoSymbol {
+            return None;
+        }
+        Some(Self::new(modifiers, keysym))
+    }
+}
+
+#[derive(Debug)]
+/// Table of the compositor keybindings.
+/// It also tracks the intercepted key presses, so that their releases are not delivered to clients either.
+pub struct KeyBindings {
+    bindings: Vec<(KeyBinding, Action)>,
+    intercepted: Vec<(usize, u32)>,
+}
+impl KeyBindings {
+    /// Create an empty keybinding table.
+    pub fn new() -> Self {
+        let bindings = Vec::new();
+        let intercepted = Vec::new();
+        Self {
+            bindings,
+            intercepted,
+        }
+    }
+
+    /// Load the keybindings from `path`.
+    /// The [default][KeyBindings::default] ones are used if the file is missing.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        path.as_ref()
+            .map(|path| match std::fs::read_to_string(path) {
+                Ok(content) => Some(Self::parse(&content)),
+                Err(error) => {
+                    if error.kind() != std::io::ErrorKind::NotFound {
+                        log::error!(target: "Keybindings","Failed to read {:?}: {}",path,error);
+                    }
+                    None
+                }
+            })
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut keybindings = Self::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line
+                .split_once('=')
+                .map(|(binding, action)| {
+                    KeyBinding::parse(binding.trim()).zip(Action::parse(action.trim()))
+                })
+                .flatten();
+            match parsed {
+                Some((binding, action)) => keybindings.bind(binding, action),
+                None => {
+                    log::error!(target: "Keybindings","Invalid line {} ignored: {}",index + 1,line)
+                }
+            }
+        }
+        keybindings
+    }
+
+    /// Bind an action to a key combination, replacing any previous action.
+    pub fn bind(&mut self, binding: KeyBinding, action: Action) {
+        self.unbind(&binding);
+        self.bindings.push((binding, action));
+    }
+
+    /// Remove the action bound to a key combination.
+    pub fn unbind(&mut self, binding: &KeyBinding) {
+        self.bindings.retain(|(bound, _action)| bound != binding);
+    }
+
+    /// Get the action bound to a key combination.
+    pub fn action(&self, modifiers: Modifiers, keysym: u32) -> Option<&Action> {
+        let binding = KeyBinding::new(modifiers, keysym);
+        self.bindings
+            .iter()
+            .find(|(bound, _action)| *bound == binding)
+            .map(|(_binding, action)| action)
+    }
+
+    /// Remember that the press of `code` on `seat` has been intercepted.
+    pub fn intercept(&mut self, seat: usize, code: u32) {
+        if !self.intercepted.contains(&(seat, code)) {
+            self.intercepted.push((seat, code));
+        }
+    }
+
+    /// Forget an intercepted press of `code` on `seat`,
+    /// returning if the related release has to be intercepted too.
+    pub fn release(&mut self, seat: usize, code: u32) -> bool {
+        let len = self.intercepted.len();
+        self.intercepted
+            .retain(|intercepted| *intercepted != (seat, code));
+        self.intercepted.len() != len
+    }
+}
+impl Default for KeyBindings {
+    /// Keybindings used when no keybindings file exists.
+    fn default() -> Self {
+        let mut keybindings = Self::new();
+        let terminal = std::env::var("TERMINAL").unwrap_or(String::from("weston-terminal"));
+        let bindings = vec![
+            (Modifiers::LOGO, ews::keysyms::KEY_q, Action::Close),
+            (Modifiers::LOGO, ews::keysyms::KEY_m, Action::Maximize),
+            (Modifiers::LOGO, ews::keysyms::KEY_Tab, Action::FocusNext),
+            (
+                Modifiers::LOGO | Modifiers::SHIFT,
+                ews::keysyms::KEY_Tab,
+                Action::FocusPrevious,
+            ),
+            (Modifiers::LOGO, ews::keysyms::KEY_o, Action::SwitchOutput),
+            (
+                Modifiers::LOGO,
+                ews::keysyms::KEY_Return,
+                Action::Spawn(terminal),
+            ),
+        ];
+        for (modifiers, keysym, action) in bindings {
+            keybindings.bind(KeyBinding::new(modifiers, keysym), action);
+        }
+        keybindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_lookup_matches_the_modifiers_exactly() {
+        let keybindings = KeyBindings::default();
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO, ews::keysyms::KEY_Tab),
+            Some(&Action::FocusNext)
+        );
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO | Modifiers::SHIFT, ews::keysyms::KEY_Tab),
+            Some(&Action::FocusPrevious)
+        );
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO | Modifiers::CTRL, ews::keysyms::KEY_Tab),
+            None
+        );
+        assert_eq!(
+            keybindings.action(Modifiers::empty(), ews::keysyms::KEY_q),
+            None
+        );
+    }
+
+    #[test]
+    fn bind_replaces_the_previous_action() {
+        let mut keybindings = KeyBindings::new();
+        let binding = KeyBinding::new(Modifiers::ALT, ews::keysyms::KEY_F4);
+        keybindings.bind(binding, Action::Maximize);
+        keybindings.bind(binding, Action::Close);
+        assert_eq!(
+            keybindings.action(Modifiers::ALT, ews::keysyms::KEY_F4),
+            Some(&Action::Close)
+        );
+        keybindings.unbind(&binding);
+        assert_eq!(
+            keybindings.action(Modifiers::ALT, ews::keysyms::KEY_F4),
+            None
+        );
+    }
+
+    #[test]
+    fn release_is_swallowed_only_after_an_intercepted_press() {
+        let mut keybindings = KeyBindings::new();
+        keybindings.intercept(0, 24);
+        keybindings.intercept(0, 24);
+        assert!(!keybindings.release(1, 24));
+        assert!(!keybindings.release(0, 25));
+        assert!(keybindings.release(0, 24));
+        assert!(!keybindings.release(0, 24));
+    }
+
+    #[test]
+    fn parse_keybindings() {
+        let keybindings = KeyBindings::parse(
+            "
+# Window management
+logo+q = close
+Logo+Shift+Tab = focus-previous
+ctrl+alt+Return = spawn foot --server
+",
+        );
+        assert_eq!(keybindings.bindings.len(), 3);
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO, ews::keysyms::KEY_q),
+            Some(&Action::Close)
+        );
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO | Modifiers::SHIFT, ews::keysyms::KEY_Tab),
+            Some(&Action::FocusPrevious)
+        );
+        assert_eq!(
+            keybindings.action(Modifiers::CTRL | Modifiers::ALT, ews::keysyms::KEY_Return),
+            Some(&Action::Spawn(String::from("foot --server")))
+        );
+    }
+
+    #[test]
+    fn parse_ignores_invalid_lines() {
+        let keybindings = KeyBindings::parse(
+            "
+hyper+q = close
+logo+NotAKey = close
+logo+m = minimize
+logo+Return = spawn
+logo+c = close now
+logo+m
+",
+        );
+        assert!(keybindings.bindings.is_empty());
+    }
+
+    #[test]
+    fn missing_file_uses_the_default_keybindings() {
+        let keybindings = KeyBindings::load(Some(PathBuf::from("/nonexistent/keybindings.conf")));
+        assert_eq!(
+            keybindings.action(Modifiers::LOGO, ews::keysyms::KEY_q),
+            Some(&Action::Close)
+        );
+    }
+}
@@ -1,6 +1,9 @@
+pub mod cursor_theme;
 pub mod event_processing;
 pub mod geometry_manager;
+pub mod keybindings;
 pub mod move_logic;
+pub mod popup_grab_logic;
 pub mod resize_logic;
 pub mod utils;
 pub mod wcomp;
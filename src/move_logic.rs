@@ -1,6 +1,8 @@
 //! Structures and enumerations related to the surface moving logic.
 
-use crate::geometry_manager::{SurfaceRequest, WCompRequest};
+use crate::geometry_manager::{
+    CursorIcon, CursorImage, CursorRequest, SeatRequest, SurfaceRequest, WCompRequest,
+};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -8,12 +10,33 @@ use std::rc::Rc;
 pub struct MoveLogic {
     start_data: ews::GrabStartData,
     requests: Rc<RefCell<Vec<WCompRequest>>>,
+    seat: usize,
+    id: usize,
+    serial: u32,
 }
 impl MoveLogic {
-    pub fn new(start_data: ews::GrabStartData, requests: Rc<RefCell<Vec<WCompRequest>>>) -> Self {
+    pub fn new(
+        start_data: ews::GrabStartData,
+        requests: Rc<RefCell<Vec<WCompRequest>>>,
+        seat: usize,
+        id: usize,
+        serial: u32,
+    ) -> Self {
+        requests.borrow_mut().push(WCompRequest::Seat {
+            request: SeatRequest::Cursor(CursorRequest::Image {
+                id: seat,
+                image: CursorImage::from(CursorIcon::Move),
+            }),
+        });
+        requests.borrow_mut().push(WCompRequest::Surface {
+            request: SurfaceRequest::InteractiveMoveStart { id, serial },
+        });
         Self {
             start_data,
             requests,
+            seat,
+            id,
+            serial,
         }
     }
 }
@@ -26,12 +49,7 @@ impl ews::PointerGrab for MoveLogic {
         _serial: ews::Serial,
         _time: u32,
     ) {
-        self.start_data.focus.as_ref().map(|(focus, position)| {
-            let id = ews::with_states(&focus, |surface_data| ews::surface_id(&surface_data))
-                .ok()
-                .flatten()
-                .unwrap();
-
+        self.start_data.focus.as_ref().map(|(_focus, position)| {
             let offset = pal::Offset2D {
                 x: self.start_data.location.x as i32 - position.x,
                 y: self.start_data.location.y as i32 - position.y,
@@ -40,12 +58,13 @@ impl ews::PointerGrab for MoveLogic {
                 x: location.x as i32,
                 y: location.y as i32,
             };
-            let position = cursor_position - offset;
+            let position = cursor_position.clone() - offset;
             let event = WCompRequest::Surface {
-                request: SurfaceRequest::Move {
-                    id,
+                request: SurfaceRequest::InteractiveMove {
+                    id: self.id,
+                    serial: self.serial,
                     position,
-                    //depth: 0,
+                    cursor: cursor_position,
                 },
             };
             self.requests.borrow_mut().push(event.into());
@@ -70,3 +89,21 @@ impl ews::PointerGrab for MoveLogic {
         &self.start_data
     }
 }
+impl Drop for MoveLogic {
+    /// The grab is dropped when released, replaced or cancelled,
+    /// the move stops and the cursor icon is reset in every case.
+    fn drop(&mut self) {
+        self.requests.borrow_mut().push(WCompRequest::Seat {
+            request: SeatRequest::Cursor(CursorRequest::Image {
+                id: self.seat,
+                image: CursorImage::default(),
+            }),
+        });
+        self.requests.borrow_mut().push(WCompRequest::Surface {
+            request: SurfaceRequest::InteractiveMoveStop {
+                id: self.id,
+                serial: self.serial,
+            },
+        });
+    }
+}
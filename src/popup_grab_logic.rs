@@ -0,0 +1,83 @@
+//! Structures and enumerations related to the popup grabbing logic.
+
+use crate::geometry_manager::{SeatRequest, WCompRequest};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Popup grabbing logic.
+/// Pointer events are delivered only while they happen on one of the grabbing popups,
+/// a button press anywhere else dismisses the whole popup chain.
+pub struct PopupGrabLogic {
+    start_data: ews::GrabStartData,
+    requests: Rc<RefCell<Vec<WCompRequest>>>,
+    seat: usize,
+    popups: Vec<usize>,
+}
+impl PopupGrabLogic {
+    pub fn new(
+        start_data: ews::GrabStartData,
+        requests: Rc<RefCell<Vec<WCompRequest>>>,
+        seat: usize,
+        popups: Vec<usize>,
+    ) -> Self {
+        Self {
+            start_data,
+            requests,
+            seat,
+            popups,
+        }
+    }
+
+    fn is_grabbing(&self, focus: Option<&(ews::WlSurface, ews::Point<i32, ews::Logical>)>) -> bool {
+        focus
+            .map(|(surface, _position)| {
+                ews::with_states(&surface, |surface_data| ews::surface_id(&surface_data))
+                    .ok()
+                    .flatten()
+            })
+            .flatten()
+            .map(|id| self.popups.contains(&id))
+            .unwrap_or(false)
+    }
+}
+impl ews::PointerGrab for PopupGrabLogic {
+    fn motion(
+        &mut self,
+        handle: &mut ews::PointerInnerHandle<'_>,
+        location: ews::Point<f64, ews::Logical>,
+        focus: Option<(ews::WlSurface, ews::Point<i32, ews::Logical>)>,
+        serial: ews::Serial,
+        time: u32,
+    ) {
+        let focus = if self.is_grabbing(focus.as_ref()) {
+            focus
+        } else {
+            None
+        };
+        handle.motion(location, focus, serial, time);
+    }
+    fn button(
+        &mut self,
+        handle: &mut ews::PointerInnerHandle<'_>,
+        button: u32,
+        state: ews::ButtonState,
+        serial: ews::Serial,
+        time: u32,
+    ) {
+        if state == ews::ButtonState::Pressed && !self.is_grabbing(handle.current_focus()) {
+            let event = WCompRequest::Seat {
+                request: SeatRequest::PopupGrabStop { id: self.seat },
+            };
+            self.requests.borrow_mut().push(event.into());
+            handle.unset_grab(serial, time);
+        } else {
+            handle.button(button, state, serial, time);
+        }
+    }
+    fn axis(&mut self, handle: &mut ews::PointerInnerHandle<'_>, details: ews::AxisFrame) {
+        handle.axis(details);
+    }
+    fn start_data(&self) -> &ews::GrabStartData {
+        &self.start_data
+    }
+}
@@ -1,6 +1,8 @@
 //! Structures and enumerations related to the surface resize logic.
 
-use crate::geometry_manager::{SurfaceRequest, WCompRequest};
+use crate::geometry_manager::{
+    CursorIcon, CursorImage, CursorRequest, SeatRequest, SurfaceRequest, WCompRequest,
+};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -8,6 +10,7 @@ use std::rc::Rc;
 pub struct ResizeLogic {
     start_data: ews::GrabStartData,
     requests: Rc<RefCell<Vec<WCompRequest>>>,
+    seat: usize,
     id: usize,
     serial: u32,
     inner_geometry: pal::Rectangle<i32, u32>,
@@ -17,17 +20,25 @@ impl ResizeLogic {
     pub fn new(
         start_data: ews::GrabStartData,
         requests: Rc<RefCell<Vec<WCompRequest>>>,
+        seat: usize,
         id: usize,
         serial: u32,
         inner_geometry: pal::Rectangle<i32, u32>,
         edge: ews::ResizeEdge,
     ) -> Self {
+        requests.borrow_mut().push(WCompRequest::Seat {
+            request: SeatRequest::Cursor(CursorRequest::Image {
+                id: seat,
+                image: CursorImage::from(CursorIcon::from(edge)),
+            }),
+        });
         requests.borrow_mut().push(WCompRequest::Surface {
             request: SurfaceRequest::InteractiveResizeStart { id, serial, edge },
         });
         Self {
             start_data,
             requests,
+            seat,
             id,
             serial,
             inner_geometry,
@@ -56,129 +67,43 @@ impl ews::PointerGrab for ResizeLogic {
             };
             let relative_cursor_position = cursor_position.clone() - position.clone();
 
-            let mut events = match self.edge {
-                ews::ResizeEdge::Right => {
-                    let inner_size = pal::Size2D {
-                        width: (relative_cursor_position.x - self.inner_geometry.position.x) as u32,
-                        height: self.inner_geometry.size.height,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::Bottom => {
-                    let inner_size = pal::Size2D {
-                        width: self.inner_geometry.size.height,
-                        height: (relative_cursor_position.y - self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::BottomRight => {
-                    let inner_size = pal::Size2D {
-                        width: (relative_cursor_position.x - self.inner_geometry.position.x) as u32,
-                        height: (relative_cursor_position.y - self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::Left => {
-                    let inner_size = pal::Size2D {
-                        width: (self.inner_geometry.size.width as i32 - relative_cursor_position.x
-                            + self.inner_geometry.position.x) as u32,
-                        height: self.inner_geometry.size.height,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::Top => {
-                    let inner_size = pal::Size2D {
-                        width: self.inner_geometry.size.width,
-                        height: (self.inner_geometry.size.height as i32
-                            - relative_cursor_position.y
-                            + self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::TopLeft => {
-                    let inner_size = pal::Size2D {
-                        width: (self.inner_geometry.size.width as i32 - relative_cursor_position.x
-                            + self.inner_geometry.position.x) as u32,
-                        height: (self.inner_geometry.size.height as i32
-                            - relative_cursor_position.y
-                            + self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::TopRight => {
-                    let inner_size = pal::Size2D {
-                        width: (relative_cursor_position.x - self.inner_geometry.position.x) as u32,
-                        height: (self.inner_geometry.size.height as i32
-                            - relative_cursor_position.y
-                            + self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
-                ews::ResizeEdge::BottomLeft => {
-                    let inner_size = pal::Size2D {
-                        width: (self.inner_geometry.size.width as i32 - relative_cursor_position.x
-                            + self.inner_geometry.position.x) as u32,
-                        height: (relative_cursor_position.y - self.inner_geometry.position.y)
-                            as u32,
-                    };
-                    vec![WCompRequest::Surface {
-                        request: SurfaceRequest::InteractiveResize {
-                            id,
-                            serial: self.serial,
-                            inner_size,
-                        },
-                    }]
-                }
+            let inner_position = &self.inner_geometry.position;
+            let inner_size = &self.inner_geometry.size;
+
+            //Sizes are computed as signed values, so that dragging an edge
+            //past the opposite one does not wrap around.
+            let width = inner_size.width as i32;
+            let height = inner_size.height as i32;
+            let right = relative_cursor_position.x - inner_position.x;
+            let left = inner_size.width as i32 - relative_cursor_position.x + inner_position.x;
+            let bottom = relative_cursor_position.y - inner_position.y;
+            let top = inner_size.height as i32 - relative_cursor_position.y + inner_position.y;
 
-                _ => Vec::new(),
+            let size = match self.edge {
+                ews::ResizeEdge::Right => Some((right, height)),
+                ews::ResizeEdge::Bottom => Some((width, bottom)),
+                ews::ResizeEdge::BottomRight => Some((right, bottom)),
+                ews::ResizeEdge::Left => Some((left, height)),
+                ews::ResizeEdge::Top => Some((width, top)),
+                ews::ResizeEdge::TopLeft => Some((left, top)),
+                ews::ResizeEdge::TopRight => Some((right, top)),
+                ews::ResizeEdge::BottomLeft => Some((left, bottom)),
+                _ => None,
             };
+
+            let mut events = size
+                .map(|(width, height)| WCompRequest::Surface {
+                    request: SurfaceRequest::InteractiveResize {
+                        id,
+                        serial: self.serial,
+                        inner_size: pal::Size2D {
+                            width: width.max(0) as u32,
+                            height: height.max(0) as u32,
+                        },
+                    },
+                })
+                .into_iter()
+                .collect::<Vec<_>>();
             self.requests.borrow_mut().append(&mut events);
         });
     }
@@ -192,12 +117,6 @@ impl ews::PointerGrab for ResizeLogic {
     ) {
         if button == self.start_data().button && state == ews::ButtonState::Released {
             handle.unset_grab(serial, time);
-            self.requests.borrow_mut().push(WCompRequest::Surface {
-                request: SurfaceRequest::InteractiveResizeStop {
-                    id: self.id,
-                    serial: self.serial,
-                },
-            });
         }
     }
     fn axis(&mut self, _handle: &mut ews::PointerInnerHandle<'_>, _details: ews::AxisFrame) {
@@ -207,3 +126,21 @@ impl ews::PointerGrab for ResizeLogic {
         &self.start_data
     }
 }
+impl Drop for ResizeLogic {
+    /// The grab is dropped when released, replaced or cancelled,
+    /// the resize stops and the cursor icon is reset in every case.
+    fn drop(&mut self) {
+        self.requests.borrow_mut().push(WCompRequest::Seat {
+            request: SeatRequest::Cursor(CursorRequest::Image {
+                id: self.seat,
+                image: CursorImage::default(),
+            }),
+        });
+        self.requests.borrow_mut().push(WCompRequest::Surface {
+            request: SurfaceRequest::InteractiveResizeStop {
+                id: self.id,
+                serial: self.serial,
+            },
+        });
+    }
+}
@@ -1,5 +1,7 @@
 //! Module containing some utility functions.
 
+use ews::Buffer;
+
 /// Convert shm format to vulkan format.
 pub fn shm_to_vulkan_format(shm_format: ews::ShmFormat) -> Option<screen_task::TextureFormat> {
     match shm_format {
@@ -8,6 +10,84 @@ pub fn shm_to_vulkan_format(shm_format: ews::ShmFormat) -> Option<screen_task::T
     }
 }
 
+/// Generate a translucent rectangle with an opaque border, used to preview surface geometries.
+pub fn outline_source(size: &pal::Size2D<u32>) -> screen_task::SurfaceSource {
+    const BORDER: u32 = 4;
+    const BORDER_COLOR: [u8; 4] = [255, 255, 255, 200];
+    const FILL_COLOR: [u8; 4] = [255, 255, 255, 40];
+    let data = (0..size.height)
+        .map(|y| {
+            (0..size.width).map(move |x| {
+                let border = x < BORDER
+                    || y < BORDER
+                    || x + BORDER >= size.width
+                    || y + BORDER >= size.height;
+                if border {
+                    BORDER_COLOR
+                } else {
+                    FILL_COLOR
+                }
+            })
+        })
+        .flatten()
+        .flatten()
+        .collect();
+    let info = screen_task::HostAllocationInfo {
+        size: [size.width, size.height],
+        stride: size.width * 4,
+        format: wgpu_engine::TextureFormat::Rgba8UnormSrgb,
+    };
+    screen_task::SurfaceSource::HostAllocation { info, data }
+}
+
+/// Get the hotspot of a surface used as cursor image.
+pub fn cursor_hotspot(surface: &ews::WlSurface) -> pal::Offset2D<i32> {
+    ews::with_states(surface, |surface_data| {
+        surface_data
+            .data_map
+            .get::<std::sync::Mutex<ews::CursorImageAttributes>>()
+            .map(|attributes| {
+                let hotspot = attributes.lock().unwrap().hotspot;
+                pal::Offset2D {
+                    x: hotspot.x,
+                    y: hotspot.y,
+                }
+            })
+    })
+    .ok()
+    .flatten()
+    .unwrap_or(pal::Offset2D { x: 0, y: 0 })
+}
+
+/// Read the current shm buffer of a surface that is not managed by the
+/// [GeometryManager][crate::geometry_manager::GeometryManager], like a client cursor,
+/// returning its content and logical size.
+pub fn surface_source(
+    surface: &ews::WlSurface,
+) -> Option<(screen_task::SurfaceSource, pal::Size2D<u32>)> {
+    ews::with_states(surface, |surface_data| {
+        let attributes = surface_data
+            .cached_state
+            .current::<ews::SurfaceAttributes>();
+        let scale = attributes.buffer_scale.max(1) as u32;
+        match attributes.buffer.as_ref() {
+            Some(ews::BufferAssignment::NewBuffer { buffer, delta: _ }) => {
+                let result = ews::with_buffer_contents(buffer, |data, info| {
+                    let size =
+                        pal::Size2D::from((info.width as u32 / scale, info.height as u32 / scale));
+                    (shm_convert_format(data, info), size)
+                })
+                .ok();
+                buffer.release();
+                result
+            }
+            _ => None,
+        }
+    })
+    .ok()
+    .flatten()
+}
+
 /// Converts shm data to [SurfaceSource][screen_task::SurfaceSource].
 pub fn shm_convert_format(data: &[u8], info: ews::BufferData) -> screen_task::SurfaceSource {
     let data = &data[info.offset as usize..(info.offset + info.height * info.stride) as usize];
@@ -1,10 +1,14 @@
 //! Module containing the main structure [WComp][WComp].
 
+use crate::cursor_theme::{CursorTheme, DrawnCursor};
 use crate::geometry_manager::*;
+use crate::keybindings::KeyBindings;
 use pal::PlatformBackend;
 use screen_task::ScreenTask;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Duration;
 use wgpu_engine::*;
 
 /// Core structure of the crate that makes possible to launch the compositor.
@@ -17,10 +21,18 @@ pub struct WComp {
     pub(crate) platform: pal::Platform,
     pub(crate) ews: ews::EmbeddedWaylandServer,
     pub(crate) geometry_manager: GeometryManager,
+    pub(crate) keybindings: KeyBindings,
+    pub(crate) cursor_theme: CursorTheme,
+    pub(crate) drawn_cursors: HashMap<usize, DrawnCursor>,
+    /// Connector names of the outputs whose surface has been requested but not created yet.
+    pub(crate) pending_output_names: HashMap<usize, String>,
     pub(crate) async_requests: Rc<RefCell<Vec<WCompRequest>>>,
     //pub(crate) default_cursor: usize
 }
 impl WComp {
+    /// Id of the screen task surface of the first seat cursor, the following seats count down from it.
+    const CURSOR_IMAGE_ID: usize = usize::MAX - 1;
+
     pub fn new() -> Self {
         let features_and_limits = ScreenTask::features_and_limits();
 
@@ -56,26 +68,20 @@ impl WComp {
         let ews = ews::EmbeddedWaylandServer::new(parameters);
         //ews.set_shm_formats(vec![Format::Argb8888,Format::Xrgb8888]);
 
-        let geometry_manager = GeometryManager::new();
+        let config = Config::load();
+        let cursor_theme = CursorTheme::resolve(
+            config.settings.cursor_theme.as_deref(),
+            config.settings.cursor_size,
+        );
+        let geometry_manager = GeometryManager::new(config);
+        let keybindings = KeyBindings::load(Config::path("keybindings.conf"));
+        let drawn_cursors = HashMap::new();
+        let pending_output_names = HashMap::new();
         let timer = std::time::Instant::now();
         let redraw_timer = std::time::Instant::now();
         let fps = 60;
         let async_requests = Rc::new(RefCell::new(Vec::new()));
 
-        //let icon_path = xcursor::CursorTheme::load("Pop").load_icon("left_ptr").unwrap();
-        //println!("Icon path: {:#?}",icon_path);
-        //screen_task::SurfaceSource::from_file_path(icon_path);
-
-        /*
-                let mut icon_file = File::open(icon_path).ok()?;
-
-                let mut buf = Vec::new();
-                let images = {
-                    icon_file.read_to_end(&mut buf).ok()?;
-                    xparser::parse_xcursor(&buf)?
-                };
-        */
-
         Self {
             timer,
             redraw_timer,
@@ -85,6 +91,10 @@ impl WComp {
             platform,
             ews,
             geometry_manager,
+            keybindings,
+            cursor_theme,
+            drawn_cursors,
+            pending_output_names,
             async_requests,
         }
     }
@@ -121,9 +131,20 @@ impl WComp {
             .unwrap();
 
         let mut redraw = false;
+        let mut cursor_wakeup: Option<std::time::Instant> = None;
         event_loop
             .run(None, self, |wcomp| {
                 redraw |= wcomp.process_messages();
+                let (cursor_redraw, next_frame) = wcomp.animate_cursors();
+                redraw |= cursor_redraw;
+                if let Some(next_frame) = next_frame {
+                    //A single wake up is kept pending for the cursor animations.
+                    let now = std::time::Instant::now();
+                    if cursor_wakeup.map(|wakeup| wakeup <= now).unwrap_or(true) {
+                        timer_handle.add_timeout(next_frame, ());
+                        cursor_wakeup = Some(now + next_frame);
+                    }
+                }
                 if redraw {
                     let current_time = wcomp.redraw_timer.elapsed();
                     let redraw_duration = std::time::Duration::from_millis(1000 / wcomp.fps as u64);
@@ -162,4 +183,123 @@ impl WComp {
         loop_handle.remove(wayland_event_source);
         loop_handle.remove(timer_event_source);
     }
+
+    /// Draw the image of the cursor of a seat at its position.
+    /// Theme icons are uploaded again only when their frame changes,
+    /// client surfaces only when `refresh` is set because they have new content.
+    pub(crate) fn draw_cursor(&mut self, id: usize, refresh: bool) {
+        let cursor = self
+            .geometry_manager
+            .cursor_ref(id)
+            .map(|cursor| (cursor.position().clone(), cursor.image().clone()));
+        let (position, image) = match cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let previous = self.drawn_cursors.get(&id).cloned();
+        let (drawn, source) = match image {
+            CursorImage::Icon(icon) => {
+                let mut drawn = match previous.clone() {
+                    Some(previous) if previous.icon == Some(icon) => previous,
+                    _ => DrawnCursor::new(Some(icon)),
+                };
+                let frames = self.cursor_theme.frames(icon);
+                let (index, _next_frame) = drawn.current_frame(frames);
+                let frame = match frames.get(index) {
+                    Some(frame) => frame.clone(),
+                    None => return self.remove_cursor_image(id),
+                };
+                let changed = previous
+                    .map(|previous| previous.icon != Some(icon) || previous.frame != index)
+                    != Some(false);
+                drawn.frame = index;
+                drawn.hotspot = frame.hotspot.clone();
+                drawn.size = frame.size.clone();
+                (drawn, Some(frame.source()).filter(|_| changed))
+            }
+            CursorImage::Surface { handle, hotspot } => {
+                let mut drawn = match previous.clone() {
+                    Some(previous) if previous.icon.is_none() => previous,
+                    _ => DrawnCursor::new(None),
+                };
+                drawn.hotspot = hotspot;
+                let changed =
+                    refresh || previous.map(|previous| previous.icon.is_some()) != Some(false);
+                let source = match changed {
+                    true => match crate::utils::surface_source(&handle) {
+                        Some((source, size)) => {
+                            drawn.size = size;
+                            Some(source)
+                        }
+                        //Nothing to show until the client commits a buffer.
+                        None => return self.remove_cursor_image(id),
+                    },
+                    false => None,
+                };
+                (drawn, source)
+            }
+            CursorImage::Hidden => return self.remove_cursor_image(id),
+        };
+
+        let geometry = self.geometry_manager.to_physical(&pal::Rectangle {
+            position: position - drawn.hotspot.clone(),
+            size: drawn.size.clone(),
+        });
+        let position = [
+            geometry.position.x,
+            geometry.position.y,
+            SurfaceManager::CURSOR_MIN_DEPTH as i32,
+        ];
+        self.wgpu_engine
+            .task_handle_cast_mut(
+                &self.screen_task,
+                |screen_task: &mut ScreenTask| match source {
+                    Some(source) => screen_task.create_surface(
+                        Self::CURSOR_IMAGE_ID - id,
+                        "",
+                        source,
+                        position,
+                        geometry.size.into(),
+                    ),
+                    None => screen_task.move_surface(Self::CURSOR_IMAGE_ID - id, position),
+                },
+            );
+        self.drawn_cursors.insert(id, drawn);
+    }
+
+    /// Stop drawing the image of the cursor of a seat.
+    pub(crate) fn remove_cursor_image(&mut self, id: usize) {
+        if self.drawn_cursors.remove(&id).is_some() {
+            self.wgpu_engine.task_handle_cast_mut(
+                &self.screen_task,
+                |screen_task: &mut ScreenTask| {
+                    screen_task.remove_surface(Self::CURSOR_IMAGE_ID - id);
+                },
+            );
+        }
+    }
+
+    /// Advance the animated cursor images, returning if they need to be redrawn
+    /// and the time left before the next frame.
+    pub(crate) fn animate_cursors(&mut self) -> (bool, Option<Duration>) {
+        let mut redraw = false;
+        let mut next_frame: Option<Duration> = None;
+        let cursors = self.drawn_cursors.clone();
+        for (id, drawn) in cursors {
+            let icon = match drawn.icon {
+                Some(icon) => icon,
+                None => continue,
+            };
+            let (index, left) = drawn.current_frame(self.cursor_theme.frames(icon));
+            if index != drawn.frame {
+                self.draw_cursor(id, false);
+                redraw = true;
+            }
+            next_frame = match (next_frame, left) {
+                (Some(next_frame), Some(left)) => Some(next_frame.min(left)),
+                (next_frame, left) => next_frame.or(left),
+            };
+        }
+        (redraw, next_frame)
+    }
 }